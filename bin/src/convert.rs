@@ -0,0 +1,26 @@
+use sl::game;
+use std::path::Path;
+
+/// Writes the strings to translate to `output`, mapped to their source text.
+pub fn extract_strings(cfg: &crate::game::Cfg, output: &Path) -> Result<(), game::LoadErr> {
+    let table = game::locale::Table::extract::<sl::default::Store>(cfg)?;
+    log::info!("Extracting {} strings to {}.", table.strings.len(), output.display());
+    game::write_file(output, &table)
+}
+
+/// Round trips through the typed representation, so the output is validated as well. Scene
+/// scripts can be converted from, but not to.
+pub fn convert(input: &Path, output: &Path, is_cfg: bool) -> Result<(), game::LoadErr> {
+    let is_cfg = is_cfg || input.file_stem().is_some_and(|stem| stem == "cfg");
+    log::info!(
+        "Converting {} {} to {}.",
+        if is_cfg { "cfg" } else { "scene" },
+        input.display(),
+        output.display(),
+    );
+    if is_cfg {
+        game::write_file(output, &game::read_file::<crate::game::Cfg>(input)?)
+    } else {
+        game::write_file(output, &game::Scene::<sl::default::Store>::read_file(input, &Default::default())?)
+    }
+}
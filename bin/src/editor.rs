@@ -5,11 +5,11 @@ use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize)]
 pub struct State {
-    game_cfg: sl::game::Cfg,
+    game_cfg: crate::game::Cfg,
 }
 
 impl State {
-    pub fn new(cfg: sl::game::Cfg) -> State {
+    pub fn new(cfg: crate::game::Cfg) -> State {
         State { game_cfg: cfg }
     }
 }
@@ -30,7 +30,7 @@ impl sl::uial::input::CustomAction for Action {
             let action = match cmd.as_str() {
                 "" => Action::Progress,
                 _ => {
-                    if let Some(n) = cmd.parse::<usize>().ok() {
+                    if let Ok(n) = cmd.parse::<usize>() {
                         Action::Select(n)
                     } else {
                         // TODO eventually consider some sort of arbitrary text input.
@@ -46,7 +46,7 @@ impl sl::uial::input::CustomAction for Action {
     }
 }
 
-impl<'a> sl::exec::Sim for State {
+impl sl::exec::Sim for State {
     type ActionEnum = Action;
     type Cfg = Cfg;
     type Store = sl::default::Store;
@@ -61,10 +61,20 @@ impl<'a> sl::exec::Sim for State {
     }
 }
 
-pub struct EditorRenTup<'a>(sl::default::RenderTup<'a, State>);
+#[allow(dead_code)]
+pub struct EditorRenTup<'a>(&'a State, &'a Cfg, sl::default::DisplayData);
+
+pub struct Render;
+
+impl<'a> display::RenderGroup<'a, State, Cfg, sl::default::DisplayData> for Render {
+    type Output = EditorRenTup<'a>;
+    fn create(a: &'a State, b: &'a Cfg, c: sl::default::DisplayData) -> Self::Output {
+        EditorRenTup(a, b, c)
+    }
+}
 
 impl<'a> std::fmt::Display for EditorRenTup<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn fmt(&self, _f: &mut std::fmt::Formatter) -> std::fmt::Result {
         unimplemented!("Rendering is not implemented for the editor.");
     }
 }
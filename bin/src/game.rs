@@ -1 +1,3 @@
-pub use sl::{game::Cfg, default::{Action, Sim}};
+pub use sl::default::{JsonRender, Sim, StreamRender, TextRender};
+
+pub type Cfg = sl::game::Cfg<sl::default::Store>;
@@ -23,7 +23,8 @@ impl Cfg {
                 ))
             })
             .chain(log_file("output.log")?);
-        let dispatch = Dispatch::new().level(self.level).chain(file_out);
+        // Everything goes through, so the level can be changed later on with `log::set_max_level`.
+        let dispatch = Dispatch::new().level(LevelFilter::Trace).chain(file_out);
         let dispatch = if self.bypass_stdio {
             dispatch
         } else {
@@ -46,21 +47,24 @@ impl Cfg {
                 .chain(std::io::stdout());
             dispatch.chain(stdout)
         };
-        dispatch.apply().map_err(Into::into)
+        dispatch.apply()?;
+        log::set_max_level(self.level);
+        Ok(())
     }
 }
 
+/// Logs only go to the log file if `quiet`, such as when stdout is kept for a protocol.
 #[cfg(debug_assertions)]
-pub fn setup() -> Result<(), InitError> {
+pub fn setup(quiet: bool) -> Result<(), InitError> {
     Cfg {
         level: LevelFilter::Debug,
-        bypass_stdio: false,
+        bypass_stdio: quiet,
     }
     .setup_logger()
 }
 
 #[cfg(not(debug_assertions))]
-pub fn setup() -> Result<(), InitError> {
+pub fn setup(_quiet: bool) -> Result<(), InitError> {
     Cfg {
         level: LevelFilter::Info,
         bypass_stdio: true,
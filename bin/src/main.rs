@@ -4,33 +4,64 @@ use tap::*;
 
 mod game;
 mod editor;
+mod convert;
 
 mod logger;
+#[allow(dead_code)]
 mod util;
 mod opts;
+mod server;
 
-fn main() -> sl::SeRes<()> {
-    logger::setup()
-        .tap_err(|e| println!("Fern logger failed to initialize due to {:?}.", e))
-        .map_err(|_| sl::exec::ResolutionErr("Fern logger failed to initialize.".to_string()))?;
+fn main() {
+    if let Err(e) = run() {
+        report(&e);
+        std::process::exit(1);
+    }
+}
 
-    log::info!("SeRen loading cmdline options.");
+fn run() -> sl::SeRes<()> {
+    // Read first, since protocols need stdout kept clear of logs.
     let opts = opts::CommandLineInterface::from_args();
+    logger::setup(opts.protocol.is_some())
+        .tap_err(|e| eprintln!("Fern logger failed to initialize due to {:?}.", e))
+        .map_err(|_| sl::exec::ResolutionErr::from("Fern logger failed to initialize.".to_string()))?;
     log::debug!("SeRen started with cmdline options {:?}.", opts);
 
+    if let Some(opts::Command::Convert { input, output, cfg }) = opts.cmd.as_ref() {
+        return convert::convert(input, output, *cfg)
+            .tap_err(|e| log::error!("Conversion failed due to {:?}.", e))
+            .map_err(Into::into);
+    }
+
     log::info!(
         "SeRen loading game cfg from {}.",
         opts.game_cfg_path.display()
     );
-    let cfg = game::Cfg::load_from(opts.game_cfg_path.as_path())
+    let mut cfg = game::Cfg::load_from(opts.game_cfg_path.as_path())
         .tap_err(|e| log::error!("Cfg failed to load due to {:?}. Shutting down.", e))?;
+    if opts.locale.is_some() {
+        cfg.locale = opts.locale.clone();
+    }
     log::debug!("SeRen loaded game cfg {:?}.", cfg);
 
+    if let Some(opts::Command::ExtractStrings { output }) = opts.cmd.as_ref() {
+        return convert::extract_strings(&cfg, output)
+            .tap_err(|e| log::error!("Extraction failed due to {:?}.", e))
+            .map_err(Into::into);
+    }
+
+    let overrides = opts.settings();
+    let settings = overrides.clone().or(sl::default::Settings::load(&cfg));
+    if let Some(level) = settings.log_level {
+        log::set_max_level(level.level_filter());
+    }
+    log::debug!("SeRen running with settings {:?}.", settings);
+
     let res = if opts.use_editor {
         log::info!("Launching SeRen in editor mode.");
-        if opts.use_raw_mode {
+        if settings.tui() {
             let input = sl::uial::input::cmd_line();
-            let display = sl::uial::display::cmd_line();
+            let display = sl::uial::display::cmd_line::<_, _, _, editor::Render>();
             log::trace!("Input and display intialized. Running editor now.");
             sl::default::run_app(
                 input,
@@ -42,7 +73,7 @@ fn main() -> sl::SeRes<()> {
             .tap_err(|e| log::error!("Editor has crashed due to {:?}.", e))
         } else {
             let input = sl::uial::input::cmd_line();
-            let display = sl::uial::display::raw_cmd_line();
+            let display = sl::uial::display::raw_cmd_line::<_, _, _, editor::Render>();
             log::trace!("Input and display intialized. Running editor now.");
             sl::default::run_app(
                 input,
@@ -53,19 +84,39 @@ fn main() -> sl::SeRes<()> {
             )
             .tap_err(|e| log::error!("Editor has crashed due to {:?}.", e))
         }
+    } else if let Some(port) = opts.serve {
+        log::info!("Launching SeRen in server mode.");
+        server::serve(port, cfg, overrides, opts.start()?)
     } else {
         log::info!("Launching SeRen in game mode.");
-        if opts.use_raw_mode {
-            let input = sl::uial::input::cmd_line();
-            let display = sl::uial::display::raw_cmd_line();
+        let sim = match opts.start()? {
+            Some(start) => game::Sim::init_starting(&cfg, Default::default(), overrides, start)?,
+            None => game::Sim::init_overriding(&cfg, Default::default(), overrides)?,
+        };
+        let events = sl::uial::event::Multiplexer::new();
+        let mut events = match opts.protocol {
+            Some(opts::Protocol::Jsonl) => events.with_input(sl::uial::input::json_lines),
+            None => events.with_input(sl::uial::input::cmd_line),
+        };
+        if opts.watch {
+            let scenes = cfg.root.join(cfg.scenes.as_path());
+            log::info!("Watching {} for changes.", scenes.display());
+            events = events.with_watch(scenes, std::time::Duration::from_millis(500));
+        }
+        if let Some(opts::Protocol::Jsonl) = opts.protocol {
+            let display = sl::uial::display::cmd_line::<_, _, _, game::JsonRender>();
+            log::trace!("Input and display intialized. Running game over JSON lines now.");
+            sl::default::run_event_loop(events, display, cfg, sim, Default::default())
+                .tap_err(|e| log::error!("Game has crashed due to {:?}.", e))
+        } else if settings.tui() {
+            let display = sl::uial::display::raw_cmd_line::<_, _, _, game::TextRender>();
             log::trace!("Input and display intialized. Running game now.");
-            sl::default::run_app(input, display, cfg, game::Sim::init(&cfg)?, Default::default())
+            sl::default::run_event_loop(events, display, cfg, sim, Default::default())
                 .tap_err(|e| log::error!("Game has crashed due to {:?}.", e))
         } else {
-            let input = sl::uial::input::cmd_line();
-            let display = sl::uial::display::cmd_line();
+            let display = sl::uial::display::cmd_line::<_, _, _, game::StreamRender>();
             log::trace!("Input and display intialized. Running game now.");
-            sl::default::run_app(input, display, cfg, game::Sim::init(&cfg)?, Default::default())
+            sl::default::run_event_loop(events, display, cfg, sim, Default::default())
                 .tap_err(|e| log::error!("Game has crashed due to {:?}.", e))
         }
     };
@@ -73,3 +124,17 @@ fn main() -> sl::SeRes<()> {
     log::trace!("Shutdown complete. Terminating.");
     res
 }
+
+/// Prints the error along with everything that caused it. Diagnostics already render everything
+/// there is to know about a bad file, so the chain stops there.
+fn report(e: &(dyn std::error::Error + 'static)) {
+    eprintln!("error: {}", e);
+    let mut source = e.source();
+    while let Some(e) = source {
+        eprintln!("caused by: {}", e);
+        if e.is::<sl::game::Diagnostic>() {
+            break;
+        }
+        source = e.source();
+    }
+}
@@ -10,6 +10,126 @@ pub struct CommandLineInterface {
     pub use_editor: bool,
     #[structopt(long = "--tui")]
     pub use_raw_mode: bool,
+    /// Characters revealed per second, overriding the game cfg.
+    #[structopt(long = "--text-speed")]
+    pub text_speed: Option<u32>,
+    /// Move on from lines without choices by themselves.
+    #[structopt(long = "--auto-advance")]
+    pub auto_advance: bool,
+    /// Show speakers without their colors.
+    #[structopt(long = "--no-color")]
+    pub no_color: bool,
+    /// Keep skipping through lines that haven't been seen before.
+    #[structopt(long = "--skip-unread")]
+    pub skip_unread: bool,
+    /// One of off, error, warn, info, debug or trace.
+    #[structopt(long = "--log-level")]
+    pub log_level: Option<sl::default::Verbosity>,
+    /// Reload scenes when their files change.
+    #[structopt(long = "--watch")]
+    pub watch: bool,
+    /// Locale to show text in, overriding the game cfg.
+    #[structopt(long = "--locale")]
+    pub locale: Option<String>,
+    /// Scene to start in, skipping the title screen. The primary scene if only `--start-line` is
+    /// given.
+    #[structopt(long = "--start-scene")]
+    pub start_scene: Option<String>,
+    /// Line to start on, counting from 0.
+    #[structopt(long = "--start-line")]
+    pub start_line: Option<usize>,
+    /// Sets a stat to start with, such as `--set gold=50` or `--set name=Alice`. Can be repeated.
+    #[structopt(long = "--set", parse(try_from_str = parse_assignment), number_of_values = 1)]
+    pub set: Vec<(String, String)>,
+    /// Store to start with, such as one from a save, written like `ints: { gold: 50 }`. `--set`
+    /// goes on top of it.
+    #[structopt(long = "--store-file")]
+    pub store_file: Option<std::path::PathBuf>,
+    /// Talk to a frontend instead of a player. `jsonl` reads an action as JSON from each line of
+    /// stdin, and writes each render as JSON on its own line of stdout.
+    #[structopt(long = "--protocol")]
+    pub protocol: Option<Protocol>,
+    /// Serve the game on this port of localhost, to play in a browser or through HTTP, with a
+    /// separate game for each session.
+    #[structopt(long = "--serve")]
+    pub serve: Option<u16>,
     #[structopt(long = "--game-cfg-path", default_value = "./game")]
     pub game_cfg_path: std::path::PathBuf,
+    #[structopt(subcommand)]
+    pub cmd: Option<Command>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Jsonl,
+}
+
+impl std::str::FromStr for Protocol {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Protocol::Jsonl),
+            _ => Err(format!("{:?} isn't a protocol. Try jsonl.", s)),
+        }
+    }
+}
+
+fn parse_assignment(s: &str) -> Result<(String, String), String> {
+    match s.find('=') {
+        Some(idx) if !s[..idx].trim().is_empty() => Ok((s[..idx].trim().to_owned(), s[idx + 1..].trim().to_owned())),
+        _ => Err(format!("{:?} should look like `name=value`.", s)),
+    }
+}
+
+impl CommandLineInterface {
+    /// Settings given as flags, which take precedence over saved ones. Flags that are off leave
+    /// the setting alone.
+    pub fn settings(&self) -> sl::default::Settings {
+        sl::default::Settings {
+            text_speed: self.text_speed,
+            auto_advance: Some(true).filter(|_| self.auto_advance),
+            color: Some(false).filter(|_| self.no_color),
+            skip_unread: Some(true).filter(|_| self.skip_unread),
+            log_level: self.log_level,
+            tui: Some(true).filter(|_| self.use_raw_mode),
+            ..Default::default()
+        }
+    }
+
+    /// Where to start the story, if any of the flags for it were given.
+    pub fn start(&self) -> Result<Option<sl::default::Start>, sl::game::LoadErr> {
+        if self.start_scene.is_none() && self.start_line.is_none() && self.set.is_empty() && self.store_file.is_none() {
+            return Ok(None);
+        }
+        let mut store: sl::default::Store = match self.store_file.as_ref() {
+            Some(path) => sl::game::read_file(path.as_path())?,
+            None => Default::default(),
+        };
+        for (name, value) in self.set.iter() {
+            store.set(name, value);
+        }
+        Ok(Some(sl::default::Start {
+            scene: self.start_scene.clone(),
+            line: self.start_line.unwrap_or(0),
+            store,
+        }))
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Converts a scene or game cfg file between YAML, JSON, TOML and RON, based on the extensions.
+    Convert {
+        input: std::path::PathBuf,
+        output: std::path::PathBuf,
+        /// Treat the input as a game cfg instead of a scene. Assumed if the file is named `cfg`.
+        #[structopt(long = "--cfg")]
+        cfg: bool,
+    },
+    /// Writes a translation template with every string in the game's scenes, in the format the
+    /// extension picks.
+    ExtractStrings {
+        output: std::path::PathBuf,
+    },
 }
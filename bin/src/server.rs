@@ -0,0 +1,293 @@
+//! Serves games over HTTP on localhost, one independent session per player.
+//!
+//! * `GET /` is a page to play in a browser.
+//! * `POST /sessions` starts a session, answering with its id.
+//! * `GET /sessions` lists sessions that are still running. Ended ones are forgotten.
+//! * `GET /sessions/{id}` is what the session shows, rendered as with `--protocol jsonl`.
+//! * `POST /sessions/{id}` takes an action, written as with `--protocol jsonl`, and answers with
+//!   what the session shows after it.
+//! * `DELETE /sessions/{id}` ends the session.
+use crate::game;
+use sl::uial::{display::Frames, input::SystemAction};
+use std::{
+    collections::BTreeMap,
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// How long to wait for a session to render after an action. Actions that don't change what's
+/// shown never render, so this is also how long those take to answer.
+const RENDER_TIMEOUT: Duration = Duration::from_millis(500);
+
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>SeRen</title></head>
+<body>
+<pre id="screen"></pre>
+<div id="choices"></div>
+<form id="entry"><input id="text"> <button>Enter</button></form>
+<button id="progress">Continue</button> <button id="menu">Menu</button>
+<script>
+let session;
+async function send(action) {
+    const res = await fetch(`/sessions/${session}`, { method: "POST", body: JSON.stringify(action) });
+    show(await res.json());
+}
+function show(state) {
+    let text = state.screen_text || `${state.speaker ? state.speaker + ": " : ""}${state.text || ""}`;
+    for (const msg of [state.error, state.notice]) {
+        if (msg) text += `\n\n${msg}`;
+    }
+    document.getElementById("screen").textContent = text;
+    const choices = document.getElementById("choices");
+    choices.replaceChildren(...state.choices.map(choice => {
+        const button = document.createElement("button");
+        button.textContent = choice.text + (choice.index === state.default_choice ? " (default)" : "");
+        button.onclick = () => send({ action: "select", value: choice.index });
+        return button;
+    }));
+}
+document.getElementById("progress").onclick = () => send({ action: "progress" });
+document.getElementById("menu").onclick = () => send({ action: "menu" });
+document.getElementById("entry").onsubmit = e => {
+    e.preventDefault();
+    const text = document.getElementById("text");
+    const number = Number(text.value);
+    send(Number.isInteger(number) && number > 0 && String(number) === text.value
+        ? { action: "select", value: number - 1 }
+        : { action: "entry", value: text.value });
+    text.value = "";
+};
+(async () => {
+    session = (await (await fetch("/sessions", { method: "POST" })).json()).id;
+    show(await (await fetch(`/sessions/${session}`)).json());
+})();
+</script>
+</body>
+</html>
+"#;
+
+struct Session {
+    tx: mpsc::Sender<SystemAction<sl::default::Action>>,
+    frames: Frames,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl Session {
+    /// Runs a game of its own on another thread, since sims can't be shared between threads. Its
+    /// saves are kept apart from other sessions'.
+    fn spawn(id: u64, mut cfg: game::Cfg, overrides: sl::default::Settings, start: Option<sl::default::Start>) -> Self {
+        cfg.saves = cfg.saves.join("sessions").join(id.to_string());
+        let (tx, input) = sl::uial::input::channel();
+        let frames = Frames::default();
+        let display_frames = frames.clone();
+        let thread = std::thread::spawn(move || {
+            let sim = match start {
+                Some(start) => game::Sim::init_starting(&cfg, Default::default(), overrides, start),
+                None => game::Sim::init_overriding(&cfg, Default::default(), overrides),
+            };
+            let res = sim.and_then(|sim| {
+                let events = sl::uial::event::Multiplexer::new().with_input(move || input);
+                let display = sl::uial::display::shared::<_, _, _, game::JsonRender>(display_frames);
+                sl::default::run_event_loop(events, display, cfg, sim, Default::default())
+            });
+            match res {
+                Ok(()) => log::info!("Session {} ended.", id),
+                Err(e) => log::error!("Session {} has crashed due to {:?}.", id, e),
+            }
+        });
+        Self { tx, frames, thread }
+    }
+}
+
+/// The frame after the first `seen` ones, or the latest if none comes in time.
+fn frame_after(frames: &Frames, seen: u64) -> String {
+    let (frame, rendered) = &**frames;
+    let frame = frame.lock().expect("Not a poisoned mutex.");
+    let (frame, _) = rendered
+        .wait_timeout_while(frame, RENDER_TIMEOUT, |frame| frame.count <= seen)
+        .expect("Not a poisoned mutex.");
+    frame.text.clone()
+}
+
+fn frame_count(frames: &Frames) -> u64 {
+    frames.0.lock().expect("Not a poisoned mutex.").count
+}
+
+/// The id after the highest one with saves, so that sessions from earlier runs keep theirs.
+fn first_id(cfg: &game::Cfg) -> u64 {
+    std::fs::read_dir(cfg.saves_dir().join("sessions"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u64>().ok())
+        .max()
+        .map_or(1, |id| id + 1)
+}
+
+struct Sessions {
+    cfg: game::Cfg,
+    overrides: sl::default::Settings,
+    start: Option<sl::default::Start>,
+    sessions: Mutex<(u64, BTreeMap<u64, Session>)>,
+}
+
+pub fn serve(
+    port: u16,
+    cfg: game::Cfg,
+    overrides: sl::default::Settings,
+    start: Option<sl::default::Start>,
+) -> sl::SeRes<()> {
+    let server = Server::http(("127.0.0.1", port))
+        .map_err(|e| std::io::Error::other(format!("Couldn't serve on port {} due to: {}", port, e)))?;
+    println!("Serving on http://127.0.0.1:{}/.", port);
+    let first = first_id(&cfg);
+    let sessions = Arc::new(Sessions {
+        cfg,
+        overrides,
+        start,
+        sessions: Mutex::new((first, BTreeMap::new())),
+    });
+    for request in server.incoming_requests() {
+        let sessions = sessions.clone();
+        // Requests can wait on a render, so they shouldn't hold up the others.
+        std::thread::spawn(move || {
+            let method = request.method().clone();
+            let url = request.url().to_owned();
+            if let Err(e) = sessions.handle(request) {
+                log::warn!("Couldn't answer {} {} due to {:?}.", method, url, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn json(status: u16, body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("A valid header.");
+    Response::from_string(body).with_status_code(status).with_header(header)
+}
+
+fn error(status: u16, msg: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json(status, serde_json::json!({ "error": msg }).to_string())
+}
+
+impl Sessions {
+    /// Also forgets sessions that have ended.
+    fn lock(&self) -> std::sync::MutexGuard<'_, (u64, BTreeMap<u64, Session>)> {
+        let mut sessions = self.sessions.lock().expect("Not a poisoned mutex.");
+        sessions.1.retain(|_, session| !session.thread.is_finished());
+        sessions
+    }
+
+    fn handle(&self, mut request: Request) -> std::io::Result<()> {
+        log::debug!("Handling {} {}.", request.method(), request.url());
+        let path: Vec<_> = request.url().split('/').filter(|part| !part.is_empty()).collect();
+        let response = match (request.method(), path.as_slice()) {
+            (Method::Get, []) => {
+                let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").expect("A valid header.");
+                Response::from_string(PAGE).with_header(header)
+            }
+            (Method::Get, ["sessions"]) => {
+                let sessions = self.lock();
+                json(200, serde_json::json!(sessions.1.keys().collect::<Vec<_>>()).to_string())
+            }
+            (Method::Post, ["sessions"]) => {
+                let mut sessions = self.lock();
+                let id = sessions.0;
+                sessions.0 += 1;
+                sessions.1.insert(id, Session::spawn(id, self.cfg.clone(), self.overrides.clone(), self.start.clone()));
+                log::info!("Started session {}.", id);
+                json(201, serde_json::json!({ "id": id }).to_string())
+            }
+            (method, ["sessions", id]) => {
+                let id = id.parse::<u64>().unwrap_or(0);
+                // Taken out of the lock, so that waiting on one session doesn't hold up the others.
+                let session = {
+                    match self.lock().1.get(&id) {
+                        Some(session) => Ok((session.tx.clone(), session.frames.clone())),
+                        None => Err(error(404, "There's no such session, or it has ended.")),
+                    }
+                };
+                match (method, session) {
+                    (_, Err(response)) => response,
+                    (Method::Get, Ok((_, frames))) => json(200, frame_after(&frames, 0)),
+                    (Method::Post, Ok((tx, frames))) => {
+                        let mut body = String::new();
+                        request.as_reader().read_to_string(&mut body)?;
+                        match sl::uial::input::parse_json(&body) {
+                            Ok(action) => {
+                                let seen = frame_count(&frames);
+                                // A session that just ended shows as gone on the next request.
+                                let _ = tx.send(action);
+                                json(200, frame_after(&frames, seen))
+                            }
+                            Err(msg) => error(400, &msg),
+                        }
+                    }
+                    (Method::Delete, Ok((tx, _))) => {
+                        self.lock().1.remove(&id);
+                        let _ = tx.send(SystemAction::Exit);
+                        log::info!("Ended session {}.", id);
+                        json(200, "{}".to_owned())
+                    }
+                    (_, Ok(_)) => error(405, "Sessions can only be read, sent actions, or ended."),
+                }
+            }
+            _ => error(404, "There's nothing here."),
+        };
+        request.respond(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A one scene game in its own directory under the system's temp dir.
+    fn project(name: &str) -> game::Cfg {
+        let root = std::env::temp_dir().join(format!("seren-server-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("scenes")).unwrap();
+        std::fs::write(root.join("cfg.yaml"), "scene_list: [a]\nscenes: ./scenes/\nprimary_scene: a\nsaves: ./saves/\n").unwrap();
+        std::fs::write(root.join("scenes").join("a.seren"), "Hello.\nBye.\n").unwrap();
+        game::Cfg::load_from(&root).unwrap()
+    }
+
+    fn text(frame: &str) -> serde_json::Value {
+        serde_json::from_str::<serde_json::Value>(frame).unwrap()["text"].clone()
+    }
+
+    #[test]
+    fn sessions_play_on_their_own_threads_and_answer_with_their_next_frame() {
+        let cfg = project("play");
+        let session = Session::spawn(1, cfg, Default::default(), None);
+        assert_eq!(text(&frame_after(&session.frames, 0)), "Hello.");
+        let seen = frame_count(&session.frames);
+        session.tx.send(sl::uial::input::parse_json(r#"{"action": "progress"}"#).unwrap()).unwrap();
+        assert_eq!(text(&frame_after(&session.frames, seen)), "Bye.");
+        session.tx.send(SystemAction::Exit).unwrap();
+        session.thread.join().unwrap();
+    }
+
+    #[test]
+    fn session_ids_carry_on_from_the_saves_of_earlier_runs() {
+        let cfg = project("ids");
+        assert_eq!(first_id(&cfg), 1);
+        let session = Session::spawn(4, cfg.clone(), Default::default(), None);
+        frame_after(&session.frames, 0);
+        let seen = frame_count(&session.frames);
+        session.tx.send(SystemAction::Action(sl::default::Action::Save(1))).unwrap();
+        frame_after(&session.frames, seen);
+        session.tx.send(SystemAction::Exit).unwrap();
+        session.thread.join().unwrap();
+
+        let save = cfg.saves_dir().join("sessions").join("4").join("slot_1.yaml");
+        assert!(save.is_file(), "{} is missing.", save.display());
+        assert_eq!(first_id(&cfg), 5);
+        // Starting it again leaves its saves alone.
+        let session = Session::spawn(4, cfg.clone(), Default::default(), None);
+        session.tx.send(SystemAction::Exit).unwrap();
+        session.thread.join().unwrap();
+        assert!(save.is_file(), "{} was cleared.", save.display());
+    }
+}
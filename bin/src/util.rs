@@ -54,7 +54,7 @@ impl<'a, T> From<&'a T> for Boo<'a, T> {
 impl<'a, T> AsRef<T> for Boo<'a, T> {
     fn as_ref(&self) -> &T {
         match self {
-            Boo::Borrowed(t) => *t,
+            Boo::Borrowed(t) => t,
             Boo::Owned(t) => t,
         }
     }
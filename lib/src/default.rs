@@ -1,12 +1,101 @@
 use crate::{uial, exec, game::{self, KeyedStore}};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, ops::RangeBounds};
+use std::{collections::{HashMap, HashSet, VecDeque}, ops::RangeBounds, time::{Duration, Instant}};
 
-#[derive(Serialize, Deserialize, Debug)]
+mod record;
+pub use record::Record;
+
+mod seen;
+pub use seen::Seen;
+
+mod achievement;
+pub use achievement::Achievements;
+
+/// Every ending the player has reached, across all playthroughs.
+pub type Endings = Record<String>;
+
+pub mod slots;
+pub use slots::{Save, Slots};
+
+mod menu;
+use menu::Screen;
+
+mod command;
+
+mod console;
+
+mod protocol;
+pub use protocol::JsonRender;
+
+pub mod hook;
+
+pub mod settings;
+pub use settings::{Settings, Verbosity};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Store {
+    #[serde(default)]
     bools: HashMap<String, bool>,
+    #[serde(default)]
     ints: HashMap<String, i64>,
+    #[serde(default)]
     nums: HashMap<String, f64>,
+    #[serde(default)]
+    strings: HashMap<String, String>,
+    /// How many of each item the player holds. Items they've run out of are left out.
+    #[serde(default)]
+    items: HashMap<String, u32>,
+}
+
+impl Store {
+    /// Sets `name` to `value`, read as a boolean or a number if it is one, and as text otherwise.
+    /// Text can be quoted, so that `"5"` stays text.
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.unset(name);
+        let name = name.to_owned();
+        if let Ok(b) = value.parse() {
+            self.bools.insert(name, b);
+        } else if let Ok(i) = value.parse() {
+            self.ints.insert(name, i);
+        } else if let Ok(n) = value.parse() {
+            self.nums.insert(name, n);
+        } else {
+            let text = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            self.strings.insert(name, text.to_owned());
+        }
+    }
+
+    fn unset(&mut self, name: &str) {
+        self.bools.remove(name);
+        self.ints.remove(name);
+        self.nums.remove(name);
+        self.strings.remove(name);
+    }
+
+    /// Takes everything in `other`, replacing whatever was under the same name.
+    pub fn merge(&mut self, other: Store) {
+        let Store { bools, ints, nums, strings, items } = other;
+        for (name, b) in bools {
+            self.unset(&name);
+            self.bools.insert(name, b);
+        }
+        for (name, i) in ints {
+            self.unset(&name);
+            self.ints.insert(name, i);
+        }
+        for (name, n) in nums {
+            self.unset(&name);
+            self.nums.insert(name, n);
+        }
+        for (name, s) in strings {
+            self.unset(&name);
+            self.strings.insert(name, s);
+        }
+        self.items.extend(items);
+    }
 }
 
 impl game::KeyedStore for Store {
@@ -15,17 +104,36 @@ impl game::KeyedStore for Store {
     fn check_keyed_guard(&self, game::guard::KeyedGuard { name, value, .. }: &game::guard::KeyedGuard<Self>) -> bool {
         match value {
             game::guard::ValueGuard::Bool(b) => {
-                self.bools.get(name.as_str()).map_or(false, |v| v == b)
+                self.bools.get(name.as_str()) == Some(b)
             }
             game::guard::ValueGuard::Int(i) => {
-                self.ints.get(name.as_str()).map_or(false, |v| i.contains(v))
+                self.ints.get(name.as_str()).is_some_and(|v| i.contains(v))
             }
             game::guard::ValueGuard::Num(n) => {
-                self.nums.get(name.as_str()).map_or(false, |v| n.contains(v))
+                self.nums.get(name.as_str()).is_some_and(|v| n.contains(v))
+            }
+            game::guard::ValueGuard::Text(t) => {
+                self.strings.get(name.as_str()) == Some(t)
             }
         }
     }
 
+    fn store_text(&mut self, name: &String, text: String) {
+        self.strings.insert(name.clone(), text);
+    }
+
+    fn item_count(&self, item: &str) -> u32 {
+        self.items.get(item).copied().unwrap_or(0)
+    }
+
+    fn set_item_count(&mut self, item: &str, count: u32) {
+        if count == 0 {
+            self.items.remove(item);
+        } else {
+            self.items.insert(item.to_owned(), count);
+        }
+    }
+
     fn update_with_value(&mut self, game::trigger::StatChange { name, change, .. }: &game::trigger::StatChange<Self>) {
         match change {
             game::trigger::ValueChange::SetBool(b) => {
@@ -37,58 +145,96 @@ impl game::KeyedStore for Store {
             game::trigger::ValueChange::SetFloat(n) => {
                 self.nums.insert(name.clone(), *n);
             }
+            // Stats that were never set count as 0, or false.
             game::trigger::ValueChange::UpdateInt(ci) => {
-                if let Some(oi) = self.ints.remove(name.as_str()) {
-                    self.ints.insert(name.clone(), oi + ci);
-                } else {
-                    panic!("Missing float for name {:?}!", name.as_str());
-                }
+                let i = self.ints.entry(name.clone()).or_default();
+                *i = i.saturating_add(*ci);
             }
             game::trigger::ValueChange::UpdateFloat(cn) => {
-                if let Some(on) = self.nums.remove(name.as_str()) {
-                    self.nums.insert(name.clone(), on + cn);
-                } else {
-                    panic!("Missing float for name {:?}!", name.as_str());
-                }
+                *self.nums.entry(name.clone()).or_default() += cn;
             }
             game::trigger::ValueChange::Custom(cmd) => {
                 match cmd.as_str() {
                     "toggleBool" => {
-                        if let Some(b) = self.bools.remove(name.as_str()) {
-                            self.bools.insert(name.clone(), !b);
-                        } else {
-                            panic!("Missing boolean value for name {:?}!", name.as_str());
-                        }
+                        let b = self.bools.entry(name.clone()).or_default();
+                        *b = !*b;
                     }
-                    _ => panic!("Unknown command {:?}!", cmd),
+                    _ => log::warn!("Skipping the unknown change {:?} to {:?}.", cmd, name),
                 }
             }
         }
     }
 }
 
-#[derive(Debug)]
+/// In JSON, tagged with `action` and holding whatever the action takes in `value`, such as
+/// `{"action": "select", "value": 0}` or `{"action": "progress"}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", content = "value", rename_all = "snake_case")]
 pub enum Action {
+    /// Picks a visible choice, counting from 0.
     Select(usize),
     Progress,
+    /// Fast-forwards through lines seen before, stopping at a choice, or at a new line unless the
+    /// settings say to skip those too.
+    Skip,
+    /// Text that isn't a command. Answers prompts, and is an unknown command anywhere else.
+    Entry(String),
     PromptRetry,
+    /// Shows what the player is carrying, until the next input.
+    Inventory,
+    /// Shows which achievements are unlocked, until the next input.
+    Achievements,
+    /// Lists the commands.
+    Help,
+    /// Undoes the last action, going back to where the player was before it.
+    Back,
+    /// Saves to the slot, counting from 1.
+    Save(usize),
+    /// Loads the slot, counting from 1.
+    Load(usize),
+    /// Shows the backlog.
+    Log,
+    Quit,
+    /// Shows everything in the store. Debug builds only.
+    #[cfg_attr(not(debug_assertions), serde(skip_deserializing))]
+    Stats,
+    /// A debug console command, typed in after a `:`. Debug builds only.
+    #[cfg_attr(not(debug_assertions), serde(skip_deserializing))]
+    Debug(String),
 }
 
 impl uial::input::CustomAction for Action {
     fn parse_input(cmd: Option<String>) -> Result<uial::input::SystemAction<Action>, String> {
         let action = if let Some(cmd) = cmd {
             log::debug!("Entry echo: {:?}", cmd);
+            // Esc, as it arrives from a line based terminal.
+            if cmd == "menu" || cmd == "\u{1b}" {
+                return Ok(uial::input::SystemAction::Menu);
+            }
             let action = match cmd.as_str() {
                 "" => Action::Progress,
+                ">>" => Action::Skip,
+                "inventory" => Action::Inventory,
+                "achievements" => Action::Achievements,
+                "help" => Action::Help,
+                "back" => Action::Back,
+                "log" => Action::Log,
+                "quit" => Action::Quit,
+                "stats" if cfg!(debug_assertions) => Action::Stats,
+                _ if cfg!(debug_assertions) && cmd.starts_with(':') => Action::Debug(cmd[1..].to_owned()),
                 _ => {
-                    if let Some(n) = cmd.parse::<usize>().ok() {
+                    // Besides commands with a slot, like `save 2`, only plain numbers count, so
+                    // that answers like `007` make it to prompts intact.
+                    if let Some(command) = Action::parse_slot_command(&cmd) {
+                        command
+                    } else if let Some(n) = cmd.parse::<usize>().ok().filter(|n| n.to_string() == cmd) {
                         if n == 0 {
                             Action::PromptRetry
                         } else {
                             Action::Select(n - 1)
                         }
                     } else {
-                        Action::Progress
+                        Action::Entry(cmd)
                     }
                 }
             };
@@ -120,39 +266,38 @@ impl LoadedScene {
         self.name.as_str()
     }
 
-    fn load_scene(name: &str, cfg: &game::Cfg) -> Result<Box<game::Scene<Store>>, game::LoadErr> {
-        use std::{fs::File, io::BufReader};
-
+    fn load_scene(name: &str, cfg: &game::Cfg<Store>) -> Result<Box<game::Scene<Store>>, game::LoadErr> {
         log::debug!("Loading scene {:?}.", name);
-        let p = cfg
-            .root
-            .join(cfg.scenes.as_path())
-            .join(format!("{}.yaml", name));
-        log::debug!("Loading scene from file {}.", p.display());
-        let f = File::open(p)?;
-        let buf = BufReader::new(f);
-        Ok(serde_yaml::from_reader(buf)?)
+        let dir = cfg.root.join(cfg.scenes.as_path());
+        log::debug!("Loading scene from directory {}.", dir.display());
+        Ok(Box::new(game::Scene::load_from(dir.as_path(), name, &cfg.characters)?))
     }
 
-    fn get_or_load(&self, cfg: &game::Cfg) -> Result<(std::sync::RwLockReadGuard<*const game::Scene<Store>>, &game::Scene<Store>), exec::ResolutionErr> {
-        let scene_guard = self.scene.read().expect("Not a poisoned mutex.");
-        if scene_guard.is_null() {
-            let scene = unsafe { &**scene_guard };
-            return Ok((scene_guard, scene));
-        } else {
-            *self.scene.write().expect("Not a poisoned mutex.") = Box::leak(
-                Self::load_scene(self.name(), cfg)
-                    .map_err(|e| format!("Scene load of {} failed with {:?}", self.name(), e))?
-            );
-            self.get_or_load(cfg)
+    fn get_or_load(&self, cfg: &game::Cfg<Store>) -> Result<(std::sync::RwLockReadGuard<'_, *const game::Scene<Store>>, &game::Scene<Store>), exec::ResolutionErr> {
+        {
+            let mut scene_guard = self.scene.write().expect("Not a poisoned mutex.");
+            if scene_guard.is_null() {
+                *scene_guard = Box::leak(
+                    Self::load_scene(self.name(), cfg)
+                        .map_err(|err| exec::ResolutionErr::SceneLoad {
+                            scene: self.name().to_owned(),
+                            err,
+                        })?
+                );
+            }
         }
+        let scene_guard = self.scene.read().expect("Not a poisoned mutex.");
+        let scene = unsafe { &**scene_guard };
+        Ok((scene_guard, scene))
     }
 }
 
 impl Drop for LoadedScene {
     fn drop(&mut self) {
-        let guard = self.scene.write().unwrap();
-        unsafe { Box::from_raw(*guard as *mut game::Scene<Store>) };
+        let scene = *self.scene.get_mut().unwrap();
+        if !scene.is_null() {
+            drop(unsafe { Box::from_raw(scene as *mut game::Scene<Store>) });
+        }
     }
 }
 
@@ -160,20 +305,383 @@ pub enum MaybeMainOrOtherScene {
     None,
     MainScene(Option<usize>),
     String(String, Option<usize>),
+    /// A trigger along the way couldn't be applied.
+    Failed(exec::ResolutionErr),
+    End(String),
+}
+
+/// Where a trigger sends the player.
+enum Jump {
+    Scene(LoadedScene, Option<usize>),
+    End(String),
 }
 
 #[derive(Default)]
 pub struct DisplayData {
     error_text: Option<String>,
+    /// Set when only more of the current line was revealed, to how many characters were shown
+    /// before.
+    revealed_from: Option<usize>,
+    /// Nothing changed except the time left to choose.
+    countdown: bool,
+    /// Ids of achievements that were just unlocked.
+    unlocked: Vec<String>,
+    /// Something to tell the player that isn't an error, such as that the game was saved.
+    notice: Option<String>,
+}
+
+/// Where the player was before an action, so recoverable errors can return them there.
+struct Checkpoint {
+    scene: String,
+    line: usize,
+    store: Store,
+}
+
+/// A character's meter, with the triggers for its thresholds compiled.
+struct Meter {
+    /// Where the meter is kept in the store, as `id.meter`.
+    key: String,
+    def: game::character::Meter<Store>,
+}
+
+impl Meter {
+    fn all(cfg: &game::Cfg<Store>) -> Vec<Self> {
+        cfg.characters
+            .iter()
+            .flat_map(|(id, character)| {
+                character.meters.iter().map(move |(name, def)| Meter {
+                    key: format!("{}.{}", id, name),
+                    def: def.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Progress of the typewriter effect on the current line.
+struct Reveal {
+    shown: usize,
+    total: usize,
+    per_char: Duration,
+    /// When the next character is due.
+    next: Instant,
 }
 
 pub struct Sim {
     pub store: Store,
     pub scene: LoadedScene,
     pub curr_line: usize,
+    /// Where the player was before each of their last actions, most recent last. Going `back`, or
+    /// recovering from an error, returns there.
+    history: VecDeque<Checkpoint>,
+    reveal: Option<Reveal>,
+    auto_advance_at: Option<Instant>,
+    /// When the current choices get picked for the player.
+    choice_deadline: Option<Instant>,
+    seen: Seen,
+    meters: Vec<Meter>,
+    hooks: hook::Hooks,
+    achievements: Achievements,
+    /// What's shown over the story, and since when. Timers wait while it's open.
+    overlay: Option<(Overlay, Instant)>,
+    /// The ending the story reached, if it's over.
+    ending: Option<String>,
+    endings: Endings,
+    exit_requested: bool,
+    slots: Slots,
+    /// Menus open over the story, with the one shown last. The story waits while any are.
+    menus: Vec<Screen>,
+    /// When the story was paused for the system menu.
+    paused_at: Option<Instant>,
+    /// Lines shown so far, oldest first, as speaker and text.
+    backlog: VecDeque<(Option<String>, String)>,
+    /// What the player chose, as saved.
+    settings: Settings,
+    /// Settings for this session only, which take precedence.
+    overrides: Settings,
+    /// Translations for the active locale.
+    strings: game::locale::Table,
+}
+
+/// A point in the story to start from instead of the beginning, such as to test a late scene
+/// without playing through everything before it.
+#[derive(Debug, Clone, Default)]
+pub struct Start {
+    /// The primary scene if unset.
+    pub scene: Option<String>,
+    pub line: usize,
+    /// Taken on top of a new game's store, so only what matters needs to be given.
+    pub store: Store,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Overlay {
+    Inventory,
+    Achievements,
 }
 
 impl Sim {
+    /// How many lines the backlog keeps, and how many actions can be undone.
+    const BACKLOG_LEN: usize = 50;
+
+    /// Starts at the first line of the primary scene.
+    pub fn init(cfg: &game::Cfg<Store>) -> crate::SeRes<Self> {
+        Self::init_with(cfg, hook::Hooks::default())
+    }
+
+    /// Like `init`, but with hooks that see everything from the first line on.
+    pub fn init_with(cfg: &game::Cfg<Store>, hooks: hook::Hooks) -> crate::SeRes<Self> {
+        Self::init_overriding(cfg, hooks, Settings::default())
+    }
+
+    /// Like `init_with`, but with settings that take precedence over the player's saved ones for
+    /// this session, such as from command line flags.
+    pub fn init_overriding(cfg: &game::Cfg<Store>, hooks: hook::Hooks, overrides: Settings) -> crate::SeRes<Self> {
+        let mut sim = Self::idle(cfg, hooks, overrides)?;
+        if cfg.title_screen.is_some() {
+            sim.menus.push(Screen::Title);
+        } else {
+            sim.new_game(cfg)?;
+        }
+        Ok(sim)
+    }
+
+    /// Like `init_overriding`, but starting from `start`, without a title screen.
+    pub fn init_starting(cfg: &game::Cfg<Store>, hooks: hook::Hooks, overrides: Settings, start: Start) -> crate::SeRes<Self> {
+        let mut sim = Self::idle(cfg, hooks, overrides)?;
+        sim.start(cfg, start)?;
+        Ok(sim)
+    }
+
+    /// Not started on anything yet.
+    fn idle(cfg: &game::Cfg<Store>, hooks: hook::Hooks, overrides: Settings) -> crate::SeRes<Self> {
+        Ok(Self {
+            store: Store::default(),
+            scene: LoadedScene::new(cfg.primary_scene.clone()),
+            curr_line: 0,
+            history: VecDeque::new(),
+            reveal: None,
+            auto_advance_at: None,
+            choice_deadline: None,
+            seen: Seen::load(cfg),
+            meters: Meter::all(cfg),
+            hooks,
+            achievements: Achievements::load(cfg),
+            overlay: None,
+            ending: None,
+            endings: Record::load(cfg, "endings.yaml"),
+            exit_requested: false,
+            slots: Slots::new(cfg),
+            menus: vec![],
+            paused_at: None,
+            backlog: VecDeque::new(),
+            settings: Settings::load(cfg),
+            overrides,
+            strings: game::locale::Table::load_active(cfg).map_err(|err| game::InitErr::Locale {
+                locale: cfg.locale.clone().unwrap_or_default(),
+                dir: cfg.locales_dir(),
+                err,
+            })?,
+        })
+    }
+
+    /// Starts the story over, from the first line of the primary scene.
+    fn new_game(&mut self, cfg: &game::Cfg<Store>) -> Result<(), exec::ResolutionErr> {
+        let store = self.new_game_store(cfg);
+        self.start_at(cfg.primary_scene.clone(), 0, store);
+        self.progress(cfg)?;
+        self.enter_line(cfg)
+    }
+
+    /// Drops whatever the player was doing, title screen included, to play on from `start`.
+    pub fn start(&mut self, cfg: &game::Cfg<Store>, Start { scene, line, store }: Start) -> Result<(), exec::ResolutionErr> {
+        let scene = scene.unwrap_or_else(|| cfg.primary_scene.clone());
+        let line_count = LoadedScene::new(scene.clone()).get_or_load(cfg)?.1.lines.len();
+        if line >= line_count {
+            return Err(format!("Scene {} only has lines 0 to {}.", scene, line_count.saturating_sub(1)).into());
+        }
+        log::info!("Starting from {}:{}.", scene, line);
+        let mut start_store = self.new_game_store(cfg);
+        start_store.merge(store);
+        self.start_at(scene, line, start_store);
+        self.progress(cfg)?;
+        self.enter_line(cfg)
+    }
+
+    /// Character vars and meters as they are at the start of the story.
+    fn new_game_store(&self, cfg: &game::Cfg<Store>) -> Store {
+        let mut store = Store::default();
+        for (id, character) in cfg.characters.iter() {
+            for (var, value) in character.vars.iter() {
+                let change = match value {
+                    game::character::Var::Bool(b) => game::trigger::ValueChange::SetBool(*b),
+                    game::character::Var::Int(i) => game::trigger::ValueChange::SetInt(*i),
+                };
+                store.update_with_value(&game::trigger::StatChange::new(format!("{}.{}", id, var), change));
+            }
+        }
+        for meter in self.meters.iter() {
+            store.ints.insert(meter.key.clone(), meter.def.clamp(meter.def.start));
+        }
+        store
+    }
+
+    /// Continues the story from a save.
+    fn load_save(&mut self, cfg: &game::Cfg<Store>, save: Save) -> Result<(), exec::ResolutionErr> {
+        log::info!("Loading save from {}:{}.", save.scene, save.line);
+        self.start_at(save.scene, save.line, save.store);
+        self.progress(cfg)?;
+        self.enter_line(cfg)
+    }
+
+    /// Puts the player at `scene:line`, as if they'd only just got there.
+    fn start_at(&mut self, scene: String, line: usize, store: Store) {
+        // Where the story starts from isn't a change.
+        self.hooks.reset(&store);
+        self.store = store;
+        self.hooks.emit(hook::Event::SceneEntered { scene: scene.clone() });
+        self.scene = LoadedScene::new(scene);
+        self.curr_line = line;
+        self.history.clear();
+        self.overlay = None;
+        self.ending = None;
+        self.menus.clear();
+        self.paused_at = None;
+        self.backlog.clear();
+    }
+
+    /// Starts revealing the current line, and schedules auto-advance once it's all there.
+    fn enter_line(&mut self, cfg: &game::Cfg<Store>) -> Result<(), exec::ResolutionErr> {
+        self.reveal = None;
+        self.auto_advance_at = None;
+        self.choice_deadline = None;
+        if self.ending.is_some() {
+            return Ok(());
+        }
+        let pacing = self.settings().pacing(cfg);
+        let (_guard, scene) = self.scene.get_or_load(cfg)?;
+        let line = match scene.lines.get(self.curr_line).and_then(|l| l.to_inner().line()) {
+            Some(line) => line,
+            None => return Ok(()),
+        };
+        self.seen.insert(self.scene.name(), self.curr_line);
+        if self.backlog.len() == Self::BACKLOG_LEN {
+            self.backlog.pop_front();
+        }
+        let text = self.strings.line(line);
+        self.backlog.push_back((line.speaker.clone(), text.to_owned()));
+        self.hooks.emit(hook::Event::LineShown {
+            scene: self.scene.name().to_owned(),
+            line: self.curr_line,
+            speaker: line.speaker.clone(),
+            text: text.to_owned(),
+        });
+        let total = text.chars().count();
+        let start = Instant::now() + line.pause.map_or(Duration::ZERO, Duration::from_millis);
+        let per_char = match line.speed.or(pacing.text_speed) {
+            Some(speed) if speed > 0 => Duration::from_secs(1) / speed,
+            // Without a speed, the whole line appears once the pause is over.
+            _ if line.pause.is_some() => Duration::ZERO,
+            _ => {
+                self.auto_advance_at = Self::auto_advance_deadline(&pacing, line, total, Instant::now());
+                self.choice_deadline = Self::choice_deadline(line, Instant::now());
+                return Ok(());
+            }
+        };
+        self.reveal = Some(Reveal {
+            shown: 0,
+            total,
+            per_char,
+            next: start + per_char,
+        });
+        Ok(())
+    }
+
+    /// `chars` is how long the line's text is, as shown.
+    fn auto_advance_deadline(pacing: &game::Pacing, line: &game::line::Line<Store>, chars: usize, from: Instant) -> Option<Instant> {
+        // Choices and prompts always wait for the player.
+        if line.choices.is_some() || line.prompt.is_some() {
+            return None;
+        }
+        pacing
+            .auto_advance
+            .as_ref()
+            .map(|auto| from + auto.delay_for(chars))
+    }
+
+    /// The clock starts once the choices are shown.
+    fn choice_deadline(line: &game::line::Line<Store>, from: Instant) -> Option<Instant> {
+        line.choices
+            .as_ref()
+            .and_then(|choices| choices.timeout)
+            .map(|ms| from + Duration::from_millis(ms))
+    }
+
+    /// Pushes back everything that's waiting on the clock, for time the story spent paused.
+    fn shift_deadlines(&mut self, paused: Duration) {
+        if let Some(reveal) = self.reveal.as_mut() {
+            reveal.next += paused;
+        }
+        self.auto_advance_at = self.auto_advance_at.map(|at| at + paused);
+        self.choice_deadline = self.choice_deadline.map(|at| at + paused);
+    }
+
+    /// The settings in effect, with this session's on top of the saved ones.
+    fn settings(&self) -> Settings {
+        self.overrides.clone().or(self.settings.clone())
+    }
+
+    fn time_left(&self) -> Option<Duration> {
+        self.choice_deadline.map(|at| at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Shows the rest of the line right away. Returns how much was shown before, if it wasn't
+    /// already all there.
+    fn finish_reveal(&mut self, cfg: &game::Cfg<Store>) -> Result<Option<usize>, exec::ResolutionErr> {
+        let shown = match self.reveal.take() {
+            Some(reveal) => reveal.shown,
+            None => return Ok(None),
+        };
+        let pacing = self.settings().pacing(cfg);
+        let (_guard, scene) = self.scene.get_or_load(cfg)?;
+        if let Some(line) = scene.lines.get(self.curr_line).and_then(|l| l.to_inner().line()) {
+            let chars = self.strings.line(line).chars().count();
+            self.auto_advance_at = Self::auto_advance_deadline(&pacing, line, chars, Instant::now());
+            self.choice_deadline = Self::choice_deadline(line, Instant::now());
+        }
+        Ok(Some(shown))
+    }
+
+    fn save_checkpoint(&mut self) {
+        if self.history.len() == Self::BACKLOG_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back(Checkpoint {
+            scene: self.scene.name().to_owned(),
+            line: self.curr_line,
+            store: self.store.clone(),
+        });
+    }
+
+    fn restore_checkpoint(&mut self) -> bool {
+        if let Some(Checkpoint { scene, line, store }) = self.history.pop_back() {
+            if scene != self.scene.name() {
+                self.jump_to_scene(LoadedScene::new(scene), Some(line));
+            }
+            self.curr_line = line;
+            self.store = store;
+            self.ending = None;
+            self.hooks.note_changes(&self.store);
+            // Whatever went wrong, the line was already shown in full. Timed choices wait for the
+            // player, since picking one for them is what might have gone wrong.
+            self.reveal = None;
+            self.auto_advance_at = None;
+            self.choice_deadline = None;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl Sim {
@@ -184,120 +692,246 @@ impl Sim {
             .map_err(|_| {
                 format!(
                         "Current line ({}:{}) doesn't exist???",
+                        self.scene.name(),
                         self.curr_line,
+                ).into()
+            })
+    }
+
+    fn is_at_prompt(&mut self, cfg: &<Self as exec::Sim>::Cfg) -> Result<bool, exec::ResolutionErr> {
+        self.scene.get_or_load(cfg)?
+            .1
+            .is_line_prompt(self.curr_line)
+            .map_err(|_| {
+                format!(
+                        "Current line ({}:{}) doesn't exist???",
                         self.scene.name(),
+                        self.curr_line,
                 ).into()
             })
     }
 
+    /// Stores the answer to the current line's prompt, if it passes validation.
+    fn answer_prompt(&mut self, cfg: &<Self as exec::Sim>::Cfg, answer: &str) -> Result<(), exec::ResolutionErr> {
+        let (_guard, scene) = self.scene.get_or_load(cfg)?;
+        let prompt = scene.lines
+            .get(self.curr_line)
+            .and_then(|l| l.to_inner().line())
+            .and_then(|l| l.prompt.as_ref())
+            .ok_or_else(|| format!(
+                "Current line ({}:{}) doesn't ask for anything.",
+                self.scene.name(),
+                self.curr_line,
+            ))?;
+        prompt.validate(answer).map_err(exec::ResolutionErr::InvalidSelection)?;
+        self.store.store_text(&prompt.into, answer.to_owned());
+        self.hooks.note_changes(&self.store);
+        Ok(())
+    }
+
     fn jump_to_scene(&mut self, scene: LoadedScene, line_num: Option<usize>) {
+        self.hooks.emit(hook::Event::SceneExited { scene: self.scene.name().to_owned() });
+        self.hooks.emit(hook::Event::SceneEntered { scene: scene.name().to_owned() });
         self.scene = scene;
         self.curr_line = line_num.unwrap_or(0);
     }
 
-    fn apply_trigger(store: &mut Store, game::trigger::Trigger {
+    /// Also fires the triggers of any meters that cross a threshold. Where they go takes precedence
+    /// over `scene_change`, though not over `end`. Their own changes don't set off further
+    /// thresholds, so they can't loop.
+    fn apply_trigger(cfg: &game::Cfg<Store>, meters: &[Meter], store: &mut Store, achievements: &mut Achievements, game::trigger::Trigger {
         scene_change,
         stats_changes,
-    }: &game::trigger::Trigger<Store>) -> Option<(LoadedScene, Option<usize>)> {
+        item_changes,
+        unlocks,
+        end,
+    }: &game::trigger::Trigger<Store>) -> Result<Option<Jump>, exec::ResolutionErr> {
+        let before: Vec<_> = meters.iter().map(|m| store.ints.get(&m.key).copied().unwrap_or(0)).collect();
         if let Some(changes) = stats_changes {
             for change in changes {
-                store.update_with_value(&change);
+                store.update_with_value(change);
             }
         }
-        scene_change
-            .as_ref()
-            .map(|new_scene| new_scene.to_inner())
-            .map(|(new_scene, line)| (LoadedScene::new(new_scene), line))
+        if let Some(changes) = item_changes {
+            for change in changes {
+                let def = cfg.items.get(change.item.as_str()).ok_or_else(|| exec::ResolutionErr::BadContent(format!(
+                    "Item {:?} isn't defined in the game cfg.",
+                    change.item,
+                )))?;
+                change.apply(store, def).map_err(exec::ResolutionErr::InvalidSelection)?;
+            }
+        }
+        let mut fired_jump = None;
+        for (meter, old) in meters.iter().zip(before) {
+            let new = meter.def.clamp(store.ints.get(&meter.key).copied().unwrap_or(old));
+            store.ints.insert(meter.key.clone(), new);
+            for threshold in meter.def.thresholds.iter() {
+                if threshold.is_crossed(old, new) {
+                    log::debug!("Meter {} went from {} to {}, crossing {:?}.", meter.key, old, new, threshold);
+                    let jump = Self::apply_trigger(cfg, &[], store, achievements, &threshold.trigger)?;
+                    fired_jump = fired_jump.or(jump);
+                }
+            }
+        }
+        achievements.update(cfg, store, unlocks.as_deref().unwrap_or(&[]))?;
+        let jump = match (end, fired_jump, scene_change) {
+            (Some(end), _, _) => Some(Jump::End(end.clone())),
+            (None, Some(fired), _) => Some(fired),
+            (None, None, Some(new_scene)) => {
+                let (new_scene, line) = new_scene.to_inner();
+                Some(Jump::Scene(LoadedScene::new(new_scene), line))
+            }
+            (None, None, None) => None,
+        };
+        Ok(jump)
+    }
+
+    fn follow(&mut self, cfg: &game::Cfg<Store>, jump: Jump) -> Result<(), exec::ResolutionErr> {
+        match jump {
+            Jump::Scene(scene, line) => {
+                self.jump_to_scene(scene, line);
+                Ok(())
+            }
+            Jump::End(id) => self.reach_ending(cfg, id),
+        }
+    }
+
+    fn reach_ending(&mut self, cfg: &game::Cfg<Store>, id: String) -> Result<(), exec::ResolutionErr> {
+        if !cfg.endings.contains_key(id.as_str()) {
+            return Err(exec::ResolutionErr::BadContent(format!(
+                "Ending {:?} isn't defined in the game cfg.",
+                id,
+            )));
+        }
+        log::info!("Reached ending {} from {}:{}.", id, self.scene.name(), self.curr_line);
+        self.endings.insert(id.clone());
+        self.hooks.emit(hook::Event::GameEnded { ending: Some(id.clone()) });
+        self.ending = Some(id);
+        self.reveal = None;
+        self.auto_advance_at = None;
+        self.choice_deadline = None;
+        Ok(())
     }
 
-    fn process_choice_selection(&mut self, cfg: &<Self as exec::Sim>::Cfg, choice: usize) -> Result<(), exec::ResolutionErr> {
-        let trigger = match self.scene.get_or_load(cfg)?.1.get_line_and_visible_choice(&self.store, self.curr_line, choice) {
-            Ok(c) => Ok(c.trigger.as_ref()),
+    /// Returns whether the choice moved the player somewhere else.
+    fn process_choice_selection(&mut self, cfg: &<Self as exec::Sim>::Cfg, choice: usize) -> Result<bool, exec::ResolutionErr> {
+        let (text, trigger) = match self.scene.get_or_load(cfg)?.1.get_line_and_visible_choice(&self.store, self.curr_line, choice) {
+            Ok(c) => Ok((self.strings.choice(c), c.trigger.as_ref())),
             Err(game::LineOrChoiceAbsenceError::LineDoesNotExist) =>
                 Err(format!(
                     "Current line ({}:{}) doesn't exist???",
-                    self.curr_line,
                     self.scene.name(),
-                )),
+                    self.curr_line,
+                ).into()),
             Err(game::LineOrChoiceAbsenceError::SelectionIsTriggerNotLine) =>
                 Err(format!(
                     "Current line ({}:{}) is a trigger, not a line.",
-                    self.curr_line,
                     self.scene.name(),
-                )),
-            Err(game::LineOrChoiceAbsenceError::Choice(game::AbsenceError::NotEnough)) =>
-                Err(format!(
-                        "Current line ({}:{}) doesn't have choices, but asked for choice {}???",
-                        self.curr_line,
-                        self.scene.name(),
-                        choice,
-                )),
-            Err(game::LineOrChoiceAbsenceError::Choice(game::AbsenceError::NotPresentAfterScan)) =>
-                Err(format!(
-                        "Current line ({}:{}) doesn't contain choice {}???",
-                        self.curr_line,
-                        self.scene.name(),
-                        choice,
-                )),
+                    self.curr_line,
+                ).into()),
+            Err(game::LineOrChoiceAbsenceError::Choice(game::AbsenceError::NoChoices)) =>
+                Err(exec::ResolutionErr::InvalidSelection(format!(
+                    "There are no choices here, so choice {} can't be picked.",
+                    choice + 1,
+                ))),
+            // Hidden choices aren't shown, so to the player they aren't there either.
+            Err(game::LineOrChoiceAbsenceError::Choice(game::AbsenceError::NotEnough | game::AbsenceError::NotPresentAfterScan)) =>
+                Err(exec::ResolutionErr::InvalidSelection(format!(
+                    "There's no choice {} here.",
+                    choice + 1,
+                ))),
         }?;
-        if let Some(t) = trigger {
-            if let Some((scene, line)) = Self::apply_trigger(&mut self.store, t) {
-                self.jump_to_scene(scene, line)
-            }
+        let jump = match trigger {
+            Some(t) => Self::apply_trigger(cfg, &self.meters, &mut self.store, &mut self.achievements, t)?,
+            None => None,
+        };
+        self.hooks.emit(hook::Event::ChoiceMade {
+            scene: self.scene.name().to_owned(),
+            line: self.curr_line,
+            text: text.to_owned(),
+            by_player: true,
+        });
+        self.hooks.note_changes(&self.store);
+        if let Some(jump) = jump {
+            self.follow(cfg, jump)?;
+            return Ok(true);
         }
-        Ok(())
+        Ok(false)
     }
 
     // TODO Combine with previous methods somehow.
-    fn process_default_choice_selection(&mut self, cfg: &<Self as exec::Sim>::Cfg) -> Result<(), exec::ResolutionErr> {
-        let trigger = match self.scene.get_or_load(cfg)?.1.get_line_and_default_choice(&self.store, self.curr_line) {
-            Ok(c) => Ok(c.trigger.as_ref()),
+    /// Returns whether the choice moved the player somewhere else.
+    /// Picks the choice for when time runs out instead, if `timed_out`.
+    fn process_default_choice_selection(&mut self, cfg: &<Self as exec::Sim>::Cfg, timed_out: bool) -> Result<bool, exec::ResolutionErr> {
+        let (guard, scene) = self.scene.get_or_load(cfg)?;
+        let choice = if timed_out {
+            scene.get_line_and_timeout_choice(&self.store, self.curr_line)
+        } else {
+            scene.get_line_and_default_choice(&self.store, self.curr_line)
+        };
+        let (text, trigger) = match choice {
+            Ok(c) => Ok((self.strings.choice(c), c.trigger.as_ref())),
             Err(game::LineOrChoiceAbsenceError::LineDoesNotExist) =>
                 Err(format!(
                     "Current line ({}:{}) doesn't exist???",
-                    self.curr_line,
                     self.scene.name(),
-                )),
+                    self.curr_line,
+                ).into()),
             Err(game::LineOrChoiceAbsenceError::SelectionIsTriggerNotLine) =>
                 Err(format!(
                     "Current line ({}:{}) is a trigger, not a line.",
-                    self.curr_line,
                     self.scene.name(),
+                    self.curr_line,
+                ).into()),
+            Err(game::LineOrChoiceAbsenceError::Choice(_)) if timed_out =>
+                Err(exec::ResolutionErr::InvalidSelection(
+                    "Time ran out, but the choice for that isn't available, so pick one of the choices.".to_owned(),
                 )),
-            Err(game::LineOrChoiceAbsenceError::Choice(game::AbsenceError::NotEnough)) =>
-                Err(format!(
-                        "Current line ({}:{}) doesn't have enough choices for the default choice???",
-                        self.curr_line,
-                        self.scene.name(),
-                )),
-            Err(game::LineOrChoiceAbsenceError::Choice(game::AbsenceError::NotPresentAfterScan)) =>
-                Err(format!(
-                        "Current line ({}:{}) doesn't contain the default choice???",
-                        self.curr_line,
-                        self.scene.name(),
+            Err(game::LineOrChoiceAbsenceError::Choice(_)) =>
+                Err(exec::ResolutionErr::InvalidSelection(
+                    "There's no default choice here, so pick one of the choices.".to_owned(),
                 )),
         }?;
-        if let Some(t) = trigger {
-            if let Some((scene, line)) = Self::apply_trigger(&mut self.store, t) {
-                self.jump_to_scene(scene, line)
-            }
+        let jump = match trigger {
+            Some(t) => Self::apply_trigger(cfg, &self.meters, &mut self.store, &mut self.achievements, t)?,
+            None => None,
+        };
+        self.hooks.emit(hook::Event::ChoiceMade {
+            scene: self.scene.name().to_owned(),
+            line: self.curr_line,
+            text: text.to_owned(),
+            by_player: false,
+        });
+        self.hooks.note_changes(&self.store);
+        drop(guard);
+        if let Some(jump) = jump {
+            self.follow(cfg, jump)?;
+            return Ok(true);
         }
-        Ok(())
+        Ok(false)
     }
 
     fn progress_to_next_line_or_scene_break(&mut self, cfg: &<Self as exec::Sim>::Cfg) -> Result<usize, MaybeMainOrOtherScene> {
-        let (_, curr_scene) = self.scene.get_or_load(cfg).map_err(|_| MaybeMainOrOtherScene::None)?;
-        for (line, idx) in (&curr_scene.lines[self.curr_line..]).iter().zip(self.curr_line..) {
+        let (_guard, curr_scene) = self.scene.get_or_load(cfg).map_err(|_| MaybeMainOrOtherScene::None)?;
+        let remaining = curr_scene.lines.get(self.curr_line..).unwrap_or(&[]);
+        for (line, idx) in remaining.iter().zip(self.curr_line..) {
             match line.try_to_inner(&self.store) {
-                Ok(game::line::LineOrTrigger::Line(_)) => {
+                Some(game::line::LineOrTrigger::Line(_)) => {
                     return Ok(idx);
                 }
-                Ok(game::line::LineOrTrigger::Trigger(trigger)) => {
-                    if let Some((scene, line)) = Self::apply_trigger(&mut self.store, trigger) {
-                        return Err(MaybeMainOrOtherScene::String(scene.name().to_owned(), line))
+                Some(game::line::LineOrTrigger::Trigger(trigger)) => {
+                    let applied = Self::apply_trigger(cfg, &self.meters, &mut self.store, &mut self.achievements, trigger);
+                    self.hooks.note_changes(&self.store);
+                    match applied {
+                        Ok(Some(Jump::Scene(scene, line))) => {
+                            return Err(MaybeMainOrOtherScene::String(scene.name().to_owned(), line))
+                        }
+                        Ok(Some(Jump::End(id))) => return Err(MaybeMainOrOtherScene::End(id)),
+                        Ok(None) => {}
+                        Err(e) => return Err(MaybeMainOrOtherScene::Failed(e)),
                     }
                 }
-                Err(_) => {}
+                None => {}
             }
         }
         // And then, if we run out of lines...
@@ -307,14 +941,22 @@ impl Sim {
                 Err(MaybeMainOrOtherScene::String(scene, line))
             }
             Some(game::MainOrSceneChange::Main(line)) => {
-                Err(MaybeMainOrOtherScene::MainScene(line.clone()))
+                Err(MaybeMainOrOtherScene::MainScene(*line))
+            }
+            Some(game::MainOrSceneChange::End(end)) => {
+                Err(MaybeMainOrOtherScene::End(end.end.clone()))
             }
             None => Err(MaybeMainOrOtherScene::None)
         }
     }
 
     fn progress(&mut self, cfg: &<Self as exec::Sim>::Cfg) -> Result<(), exec::ResolutionErr> {
+        if self.ending.is_some() {
+            return Ok(());
+        }
         loop {
+            // Surface load failures, instead of having them look like a soft lock.
+            drop(self.scene.get_or_load(cfg)?);
             match self.progress_to_next_line_or_scene_break(cfg) {
                 Err(MaybeMainOrOtherScene::String(name, line)) => {
                     self.jump_to_scene(LoadedScene::new(name), line)
@@ -322,12 +964,14 @@ impl Sim {
                 Err(MaybeMainOrOtherScene::MainScene(line)) => {
                     self.jump_to_scene(LoadedScene::new(cfg.primary_scene.clone()), line)
                 }
+                Err(MaybeMainOrOtherScene::Failed(e)) => return Err(e),
+                Err(MaybeMainOrOtherScene::End(id)) => return self.reach_ending(cfg, id),
                 Err(MaybeMainOrOtherScene::None) => {
-                    return Err(format!(
+                    return Err(exec::ResolutionErr::SoftLock(format!(
                             "Trying to proceed from current line ({}:{}) leads to a soft lock. Was this intentional?",
-                            self.curr_line,
                             self.scene.name(),
-                    ).into());
+                            self.curr_line,
+                    )));
                 }
                 Ok(line_num) => {
                     self.curr_line = line_num;
@@ -337,11 +981,176 @@ impl Sim {
         }
         Ok(())
     }
+
+    fn resolve_action(
+        &mut self,
+        cfg: &game::Cfg<Store>,
+        a: Action,
+    ) -> std::result::Result<uial::display::RenderMode<DisplayData>, exec::ResolutionErr> {
+        if let Some(screen) = self.menus.last().copied() {
+            return self.resolve_menu(cfg, screen, a);
+        }
+        if let Some((_, opened_at)) = self.overlay.take() {
+            // Whatever the input was, it only closes the overlay. Time spent looking doesn't count.
+            self.shift_deadlines(opened_at.elapsed());
+            return Ok(uial::display::RenderMode::Render(DisplayData::default()));
+        }
+        // Even at a prompt, since the console is for looking under the hood.
+        if let Action::Debug(input) = &a {
+            return self.resolve_console(cfg, input);
+        }
+        // At a prompt, commands are answers like any other.
+        if self.ending.is_some() || !self.is_at_prompt(cfg)? {
+            if let Some(render_mode) = self.resolve_command(cfg, &a)? {
+                return Ok(render_mode);
+            }
+        }
+        if self.ending.is_some() {
+            if !matches!(a, Action::Progress) {
+                return Ok(uial::display::RenderMode::Render(DisplayData::default()));
+            }
+            if cfg.title_screen.is_none() {
+                self.exit_requested = true;
+                return Ok(uial::display::RenderMode::Ignore);
+            }
+            self.menus.push(Screen::Title);
+            return Ok(uial::display::RenderMode::Render(DisplayData::default()));
+        }
+        let overlay = match a {
+            Action::Inventory => Some(Overlay::Inventory),
+            Action::Achievements => Some(Overlay::Achievements),
+            _ => None,
+        };
+        if let Some(overlay) = overlay {
+            // At a prompt, it's an answer like any other.
+            if !self.is_at_prompt(cfg)? {
+                self.finish_reveal(cfg)?;
+                self.overlay = Some((overlay, Instant::now()));
+                return Ok(uial::display::RenderMode::Render(DisplayData::default()));
+            }
+        }
+        // Any input while a line is being revealed just shows the rest of it. Skipping goes on to skip,
+        // from the line's end, with its timers started.
+        if matches!(a, Action::Skip) {
+            self.finish_reveal(cfg)?;
+        } else if let Some(from) = self.finish_reveal(cfg)? {
+            return Ok(uial::display::RenderMode::Render(DisplayData {
+                revealed_from: Some(from),
+                ..Default::default()
+            }));
+        }
+        if self.is_at_prompt(cfg)? {
+            let answer = match a {
+                Action::Entry(text) => text,
+                Action::Progress => String::new(),
+                // Numbers are still answers here.
+                Action::Select(n) => (n + 1).to_string(),
+                Action::PromptRetry => "0".to_owned(),
+                Action::Skip => {
+                    return Ok(uial::display::RenderMode::Render(DisplayData {
+                        error_text: Some("There's a question to answer first.".to_owned()),
+                        ..Default::default()
+                    }));
+                }
+                command => command.command_text().unwrap_or_default(),
+            };
+            self.save_checkpoint();
+            self.answer_prompt(cfg, answer.trim())?;
+            self.curr_line += 1;
+            self.progress(cfg)?;
+            self.enter_line(cfg)?;
+            return Ok(uial::display::RenderMode::Render(DisplayData::default()));
+        }
+        let render_mode = match a {
+            Action::Select(choice) => {
+                self.save_checkpoint();
+                if !self.process_choice_selection(cfg, choice)? {
+                    self.curr_line += 1;
+                }
+                self.progress(cfg)?;
+                self.enter_line(cfg)?;
+                uial::display::RenderMode::Render(DisplayData::default())
+            }
+            Action::Progress => {
+                self.save_checkpoint();
+                let jumped = if self.is_at_choice(cfg)? {
+                    self.process_default_choice_selection(cfg, false)?
+                } else {
+                    false
+                };
+                if !jumped {
+                    self.curr_line += 1;
+                }
+                self.progress(cfg)?;
+                self.enter_line(cfg)?;
+                uial::display::RenderMode::Render(DisplayData::default())
+            }
+            Action::Skip => {
+                self.save_checkpoint();
+                let skip_unread = self.settings().skip_unread();
+                let mut visited = HashSet::new();
+                let mut skipped = false;
+                while !self.is_at_choice(cfg)? && !self.is_at_prompt(cfg)? {
+                    self.curr_line += 1;
+                    self.progress(cfg)?;
+                    skipped = true;
+                    if self.ending.is_some() {
+                        // Stay on the last line, rather than one past the end of the scene.
+                        let (_guard, scene) = self.scene.get_or_load(cfg)?;
+                        self.curr_line = self.curr_line.min(scene.lines.len().saturating_sub(1));
+                        break;
+                    }
+                    let seen = self.seen.contains(self.scene.name(), self.curr_line);
+                    // Stop if the story loops back on itself, instead of skipping forever.
+                    let looped = !visited.insert((self.scene.name().to_owned(), self.curr_line));
+                    self.enter_line(cfg)?;
+                    if (!seen && !skip_unread) || looped {
+                        break;
+                    }
+                }
+                uial::display::RenderMode::Render(DisplayData {
+                    error_text: if skipped { None } else { Some("There's a choice to make first.".to_owned()) },
+                    ..Default::default()
+                })
+            }
+            // Commands, and unknown ones, were already taken care of.
+            Action::PromptRetry
+            | Action::Inventory
+            | Action::Achievements
+            | Action::Entry(_)
+            | Action::Help
+            | Action::Back
+            | Action::Save(_)
+            | Action::Load(_)
+            | Action::Log
+            | Action::Quit
+            | Action::Stats
+            | Action::Debug(_) => uial::display::RenderMode::Ignore,
+        };
+        Ok(render_mode)
+    }
+
+    /// Shows achievements unlocked since the last render, with a full frame if need be.
+    fn announce(&mut self, render_mode: uial::display::RenderMode<DisplayData>) -> uial::display::RenderMode<DisplayData> {
+        let unlocked = self.achievements.take_announcements();
+        if unlocked.is_empty() {
+            return render_mode;
+        }
+        let error_text = match render_mode {
+            uial::display::RenderMode::Render(data) => data.error_text,
+            uial::display::RenderMode::Ignore => None,
+        };
+        uial::display::RenderMode::Render(DisplayData {
+            error_text,
+            unlocked,
+            ..Default::default()
+        })
+    }
 }
 
 impl exec::Sim for Sim {
     type ActionEnum = Action;
-    type Cfg = game::Cfg;
+    type Cfg = game::Cfg<Store>;
 
     type Store = Store;
 
@@ -352,34 +1161,172 @@ impl exec::Sim for Sim {
         cfg: &Self::Cfg,
         a: Self::ActionEnum,
     ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, exec::ResolutionErr> {
-        let render_mode = match a {
-            Self::ActionEnum::Select(choice) => {
-                self.process_choice_selection(cfg, choice)?;
-                self.progress(cfg)?;
-                uial::display::RenderMode::Render(DisplayData {
-                    error_text: None,
-                })
+        let render_mode = self.resolve_action(cfg, a)?;
+        Ok(self.announce(render_mode))
+    }
+
+    fn recover(
+        &mut self,
+        _cfg: &Self::Cfg,
+        e: exec::ResolutionErr,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, exec::ResolutionErr> {
+        if !e.is_recoverable() || !self.restore_checkpoint() {
+            return Err(e);
+        }
+        log::warn!("Recovered from {:?} by returning to {}:{}.", e, self.scene.name(), self.curr_line);
+        Ok(self.announce(uial::display::RenderMode::Render(DisplayData {
+            error_text: Some(error_text(&e)),
+            ..Default::default()
+        })))
+    }
+
+    fn next_deadline(&self) -> Option<Instant> {
+        if self.overlay.is_some() || self.ending.is_some() || !self.menus.is_empty() {
+            return None;
+        }
+        if let Some(reveal) = self.reveal.as_ref() {
+            return Some(reveal.next);
+        }
+        // Wake up whenever the countdown drops to the next whole second.
+        let tick = self.time_left().map(|left| {
+            let to_next_second = left - Duration::from_secs(left.as_secs());
+            Instant::now() + if to_next_second.is_zero() { Duration::from_secs(1) } else { to_next_second }
+        });
+        [self.auto_advance_at, tick, self.choice_deadline].iter().flatten().min().copied()
+    }
+
+    fn on_timer(
+        &mut self,
+        cfg: &Self::Cfg,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, exec::ResolutionErr> {
+        let now = Instant::now();
+        if self.overlay.is_some() {
+            return Ok(uial::display::RenderMode::Ignore);
+        }
+        if let Some(reveal) = self.reveal.as_mut() {
+            if now < reveal.next {
+                return Ok(uial::display::RenderMode::Ignore);
             }
-            Self::ActionEnum::Progress => {
-                if self.is_at_choice(cfg)? {
-                    self.process_default_choice_selection(cfg)?;
-                }
-                self.progress(cfg)?;
-                uial::display::RenderMode::Render(DisplayData {
-                    error_text: None,
-                })
+            let from = reveal.shown;
+            // Catch up on every character that's due, in case the timer fired late.
+            let due = match reveal.per_char.as_nanos() {
+                0 => reveal.total,
+                per_char => 1 + ((now - reveal.next).as_nanos() / per_char) as usize,
+            };
+            reveal.shown = (from + due).min(reveal.total);
+            reveal.next += reveal.per_char * due as u32;
+            if reveal.shown == reveal.total {
+                self.finish_reveal(cfg)?;
             }
-            Self::ActionEnum::PromptRetry => {
-                uial::display::RenderMode::Ignore
+            return Ok(uial::display::RenderMode::Render(DisplayData {
+                revealed_from: Some(from),
+                ..Default::default()
+            }));
+        }
+        if self.choice_deadline.is_some_and(|at| at <= now) {
+            log::debug!("Time ran out at {}:{}.", self.scene.name(), self.curr_line);
+            self.choice_deadline = None;
+            self.save_checkpoint();
+            if !self.process_default_choice_selection(cfg, true)? {
+                self.curr_line += 1;
             }
-        };
-        Ok(render_mode)
+            self.progress(cfg)?;
+            self.enter_line(cfg)?;
+            return Ok(self.announce(uial::display::RenderMode::Render(DisplayData::default())));
+        }
+        if self.choice_deadline.is_some() {
+            return Ok(uial::display::RenderMode::Render(DisplayData {
+                countdown: true,
+                ..Default::default()
+            }));
+        }
+        if self.auto_advance_at.is_some_and(|at| at <= now) {
+            self.auto_advance_at = None;
+            return self.resolve(cfg, Action::Progress);
+        }
+        Ok(uial::display::RenderMode::Ignore)
+    }
+
+    /// Shown over whatever's on screen, which stays as it was.
+    fn on_invalid_input(
+        &mut self,
+        _cfg: &Self::Cfg,
+        msg: String,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, exec::ResolutionErr> {
+        Ok(uial::display::RenderMode::Render(DisplayData {
+            error_text: Some(msg),
+            ..Default::default()
+        }))
+    }
+
+    fn open_menu(
+        &mut self,
+        _cfg: &Self::Cfg,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, exec::ResolutionErr> {
+        match self.menus.last() {
+            // There's no story to pause yet.
+            Some(Screen::Title) => return Ok(uial::display::RenderMode::Ignore),
+            // Backs out of whatever menu is open, and out of the system menu itself.
+            Some(_) => self.close_menu(),
+            None if self.ending.is_some() => return Ok(uial::display::RenderMode::Ignore),
+            None => self.pause(Screen::Pause),
+        }
+        Ok(uial::display::RenderMode::Render(DisplayData::default()))
+    }
+
+    fn wants_exit(&self) -> bool {
+        self.exit_requested
+    }
+
+    fn on_exit(&mut self, _cfg: &Self::Cfg) {
+        // Reaching an ending already counts, and so does quitting to the title screen.
+        if self.ending.is_none() && !self.menus.contains(&Screen::Title) {
+            self.hooks.emit(hook::Event::GameEnded { ending: None });
+        }
+    }
+
+    /// Reloads the current scene if its file changed. If the new version doesn't load, the old one
+    /// is kept, so a half-written file doesn't end the session.
+    fn on_files_changed(
+        &mut self,
+        cfg: &Self::Cfg,
+        paths: &[std::path::PathBuf],
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, exec::ResolutionErr> {
+        let name = self.scene.name();
+        if !paths.iter().any(|p| p.file_stem().is_some_and(|stem| stem == name)) {
+            return Ok(uial::display::RenderMode::Ignore);
+        }
+        let scene = LoadedScene::new(name.to_owned());
+        if let Err(e) = scene.get_or_load(cfg) {
+            log::warn!("Reloading scene {} failed due to {:?}.", name, e);
+            return Ok(uial::display::RenderMode::Render(DisplayData {
+                error_text: Some(error_text(&e)),
+                ..Default::default()
+            }));
+        }
+        log::info!("Reloaded scene {}.", name);
+        self.scene = scene;
+        self.save_checkpoint();
+        self.progress(cfg)?;
+        self.enter_line(cfg)?;
+        Ok(uial::display::RenderMode::Render(DisplayData::default()))
+    }
+}
+
+/// Includes the diagnostic for scenes that failed to load, so authors can find the mistake.
+fn error_text(e: &exec::ResolutionErr) -> String {
+    match e {
+        exec::ResolutionErr::SceneLoad { err, .. } => err
+            .diagnostic()
+            .map_or_else(|| e.to_string(), |d| format!("{}\n{}", e, d)),
+        _ => e.to_string(),
     }
 }
 
 struct FilteredChoices<'a> {
     choices: &'a game::line::Choices<Store>,
     store: &'a <Sim as exec::Sim>::Store,
+    strings: &'a game::locale::Table,
 }
 
 impl<'a> std::fmt::Display for FilteredChoices<'a> {
@@ -387,34 +1334,81 @@ impl<'a> std::fmt::Display for FilteredChoices<'a> {
         let mut counter = 1;
         for (choice_num, choice) in self.choices.choices.iter().enumerate() {
             if let Some(guard) = choice.guard.as_ref() {
-                if !self.store.check_guard(&guard)  {
+                if !self.store.check_guard(guard)  {
                     continue;
                 }
             }
+            write!(fmt, "{}) {}", counter, self.strings.choice(choice))?;
             counter += 1;
-            write!(fmt, "{}) {}", counter, choice.text)?;
             if choice_num == self.choices.default_choice {
                 write!(fmt, " (default choice)")?;
             }
-            write!(fmt, "\n")?;
+            writeln!(fmt)?;
         }
         Ok(())
     }
 }
 
+/// Leaves `s` as it is if stdout isn't a terminal, or `NO_COLOR` is set.
+fn paint(color: game::character::Color, s: &str) -> colored::ColoredString {
+    use colored::Colorize;
+    use game::character::Color;
+    s.color(match color {
+        Color::Black => colored::Color::Black,
+        Color::Red => colored::Color::Red,
+        Color::Green => colored::Color::Green,
+        Color::Yellow => colored::Color::Yellow,
+        Color::Blue => colored::Color::Blue,
+        Color::Magenta => colored::Color::Magenta,
+        Color::Cyan => colored::Color::Cyan,
+        Color::White => colored::Color::White,
+    })
+}
+
 struct FilteredLine<'a> {
     line: &'a game::line::Line<Store>,
+    characters: &'a std::collections::BTreeMap<String, game::character::Character<Store>>,
     store: &'a <Sim as exec::Sim>::Store,
+    strings: &'a game::locale::Table,
+    /// Whether to paint speakers in their colors.
+    color: bool,
+    /// How many characters of the text are revealed, if not all of them.
+    shown: Option<usize>,
+    /// Only write the text after this many characters, for displays that keep what was already
+    /// written.
+    from: Option<usize>,
 }
 
 impl<'a> std::fmt::Display for FilteredLine<'a> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Some(speaker) = &self.line.speaker {
-            write!(fmt, "{}: ", speaker)?;
+        if let (Some(speaker), None) = (&self.line.speaker, self.from) {
+            match game::character::find(self.characters, speaker) {
+                Some((_, character)) => {
+                    let name = self.strings.translate(None, &character.name);
+                    match character.color.filter(|_| self.color) {
+                        Some(color) => write!(fmt, "{}", paint(color, name))?,
+                        None => write!(fmt, "{}", name)?,
+                    }
+                    if let Some(title) = character.title.as_ref() {
+                        write!(fmt, " ({})", self.strings.translate(None, title))?;
+                    }
+                    write!(fmt, ": ")?;
+                }
+                None => write!(fmt, "{}: ", self.strings.translate(None, speaker))?,
+            }
+        }
+        let from = self.from.unwrap_or(0);
+        let text = self.strings.line(self.line).chars().skip(from);
+        if let Some(shown) = self.shown {
+            // The rest of the line, and the choices, show up once the text is all there.
+            return text.take(shown.saturating_sub(from)).try_for_each(|c| write!(fmt, "{}", c));
         }
-        write!(fmt, "{}", self.line.text)?;
-        if let Some(choices) = &self.line.choices {
-            write!(fmt, "{}\n", FilteredChoices { choices, store: self.store })?;
+        text.clone().try_for_each(|c| write!(fmt, "{}", c))?;
+        writeln!(fmt)?;
+        if self.line.prompt.is_some() {
+            write!(fmt, "> ")?;
+        } else if let Some(choices) = &self.line.choices {
+            write!(fmt, "{}", FilteredChoices { choices, store: self.store, strings: self.strings })?;
         }
         Ok(())
     }
@@ -422,19 +1416,19 @@ impl<'a> std::fmt::Display for FilteredLine<'a> {
 
 pub struct RenderTup<'a, Sim: exec::Sim>(&'a Sim, &'a Sim::Cfg, Sim::DisplayData);
 
-impl<'a> uial::display::RenderGroup<'a, Sim, game::Cfg, DisplayData> for RenderTup<'a, Sim> {
-    fn create(a: &'a Sim, b: &'a game::Cfg, c: DisplayData) -> Self {
-        Self(a, b, c)
+/// Renders the game as plain text, redrawing the whole line every time.
+pub struct TextRender;
+
+impl<'a> uial::display::RenderGroup<'a, Sim, game::Cfg<Store>, DisplayData> for TextRender {
+    type Output = RenderTup<'a, Sim>;
+    fn create(a: &'a Sim, b: &'a game::Cfg<Store>, c: DisplayData) -> Self::Output {
+        RenderTup(a, b, c)
     }
 }
 
-impl<'a> std::fmt::Display for RenderTup<'a, Sim> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let RenderTup(sim, cfg, data) = self;
-        if let Some(text) = data.error_text.as_ref() {
-            write!(f, "Error encountered: {}", text)?;
-        }
-        // Display the line, even if there was an error.
+impl<'a> RenderTup<'a, Sim> {
+    fn fmt_line(&self, f: &mut std::fmt::Formatter, from: Option<usize>) -> std::fmt::Result {
+        let RenderTup(sim, cfg, _) = self;
         if let Some(line) = sim.scene.get_or_load(cfg).unwrap().1.lines.get(sim.curr_line) {
             let line = match &line.to_inner() {
                 game::line::LineOrTrigger::Line(l) => {l},
@@ -448,7 +1442,12 @@ impl<'a> std::fmt::Display for RenderTup<'a, Sim> {
                 "{}",
                 FilteredLine {
                     line,
+                    characters: &cfg.characters,
                     store: &sim.store,
+                    strings: &sim.strings,
+                    color: sim.settings().color(),
+                    shown: sim.reveal.as_ref().map(|r| r.shown),
+                    from,
                 }
             )
         } else {
@@ -457,9 +1456,146 @@ impl<'a> std::fmt::Display for RenderTup<'a, Sim> {
     }
 }
 
+impl<'a> RenderTup<'a, Sim> {
+    /// Lists held items in the order the game cfg defines them.
+    fn fmt_inventory(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let RenderTup(sim, cfg, _) = self;
+        writeln!(f, "Inventory:")?;
+        let mut empty = true;
+        for (id, item) in cfg.items.iter() {
+            let count = sim.store.item_count(id);
+            if count == 0 {
+                continue;
+            }
+            empty = false;
+            write!(f, "  {} x{}", item.name.as_deref().unwrap_or(id), count)?;
+            if let Some(description) = item.description.as_ref() {
+                write!(f, " - {}", description)?;
+            }
+            writeln!(f)?;
+        }
+        if empty {
+            writeln!(f, "  Nothing.")?;
+        }
+        writeln!(f, "(Press enter to go back.)")
+    }
+
+    fn fmt_ending(&self, f: &mut std::fmt::Formatter, id: &str) -> std::fmt::Result {
+        let RenderTup(sim, cfg, _) = self;
+        writeln!(f, "THE END")?;
+        if let Some(ending) = cfg.endings.get(id) {
+            writeln!(f, "{}", ending.title)?;
+            if let Some(description) = ending.description.as_ref() {
+                writeln!(f, "{}", description)?;
+            }
+        }
+        let reached = cfg.endings.keys().filter(|id| sim.endings.contains(id.as_str())).count();
+        writeln!(f, "Endings reached: {}/{}", reached, cfg.endings.len())?;
+        if cfg.title_screen.is_some() {
+            writeln!(f, "(Press enter to return to the title screen.)")
+        } else {
+            writeln!(f, "(Press enter to quit.)")
+        }
+    }
+
+    fn fmt_achievements(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let RenderTup(sim, cfg, _) = self;
+        let unlocked = cfg.achievements.keys().filter(|id| sim.achievements.is_unlocked(id)).count();
+        writeln!(f, "Achievements ({}/{}):", unlocked, cfg.achievements.len())?;
+        for (id, achievement) in cfg.achievements.iter() {
+            let mark = if sim.achievements.is_unlocked(id) { 'x' } else { ' ' };
+            write!(f, "  [{}] {}", mark, achievement.title)?;
+            if let Some(description) = achievement.description.as_ref() {
+                write!(f, " - {}", description)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "(Press enter to go back.)")
+    }
+}
+
+impl<'a> std::fmt::Display for RenderTup<'a, Sim> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(text) = self.2.error_text.as_ref() {
+            writeln!(f, "Error encountered: {}", text)?;
+        }
+        if let Some(text) = self.2.notice.as_ref() {
+            writeln!(f, "{}", text)?;
+        }
+        for id in self.2.unlocked.iter() {
+            let title = self.1.achievements.get(id).map_or(id.as_str(), |a| a.title.as_str());
+            writeln!(f, "Achievement unlocked: {}!", title)?;
+        }
+        if let Some(screen) = self.0.menus.last() {
+            return self.fmt_menu(f, *screen);
+        }
+        match self.0.overlay {
+            Some((Overlay::Inventory, _)) => return self.fmt_inventory(f),
+            Some((Overlay::Achievements, _)) => return self.fmt_achievements(f),
+            None => {}
+        }
+        if let Some(id) = self.0.ending.as_ref() {
+            return self.fmt_ending(f, id);
+        }
+        // Display the line, even if there was an error.
+        self.fmt_line(f, None)?;
+        if let (Some(left), None) = (self.0.time_left(), self.0.reveal.as_ref()) {
+            writeln!(f, "{}s left to choose.", whole_seconds(left))?;
+        }
+        Ok(())
+    }
+}
+
+/// Rounded up, so the countdown ends at 1 rather than 0.
+fn whole_seconds(d: Duration) -> u64 {
+    (d.as_millis() as u64).div_ceil(1000)
+}
+
+pub struct StreamTup<'a, Sim: exec::Sim>(RenderTup<'a, Sim>);
+
+/// Renders the game as plain text, only writing what's new when more of a line is revealed. For
+/// displays that can't redraw.
+pub struct StreamRender;
+
+impl<'a> uial::display::RenderGroup<'a, Sim, game::Cfg<Store>, DisplayData> for StreamRender {
+    type Output = StreamTup<'a, Sim>;
+    fn create(a: &'a Sim, b: &'a game::Cfg<Store>, c: DisplayData) -> Self::Output {
+        StreamTup(RenderTup(a, b, c))
+    }
+}
+
+impl<'a> std::fmt::Display for StreamTup<'a, Sim> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let RenderTup(sim, _, data) = &self.0;
+        match (data.revealed_from, sim.time_left()) {
+            (_, Some(left)) if data.countdown => write!(f, "{}... ", whole_seconds(left)),
+            (Some(from), left) => {
+                self.0.fmt_line(f, Some(from))?;
+                // The choices just appeared, so the countdown did too.
+                match left {
+                    Some(left) if sim.reveal.is_none() => writeln!(f, "{}s left to choose.", whole_seconds(left)),
+                    _ => Ok(()),
+                }
+            }
+            _ => write!(f, "\n{}", self.0),
+        }
+    }
+}
+
 // in, out, stable state, unstable state
 pub fn run_app<Sim: exec::Sim, RenderData>(
-    mut input: impl uial::input::Input<Sim::ActionEnum>,
+    input: impl uial::input::Input<Sim::ActionEnum>,
+    display: impl uial::display::Display<Sim, Sim::Cfg, Sim::DisplayData, RenderData>,
+    cfg: Sim::Cfg,
+    sim: Sim,
+    init_disp_data: Sim::DisplayData,
+) -> crate::SeRes<()> {
+    run_event_loop(uial::event::Blocking(input), display, cfg, sim, init_disp_data)
+}
+
+/// Like `run_app`, but also reacts to timers and file changes, if `events` produces them.
+pub fn run_event_loop<Sim: exec::Sim, RenderData>(
+    mut events: impl uial::event::EventSource<Sim::ActionEnum>,
     mut display: impl uial::display::Display<Sim, Sim::Cfg, Sim::DisplayData, RenderData>,
     cfg: Sim::Cfg,
     mut sim: Sim,
@@ -468,21 +1604,39 @@ pub fn run_app<Sim: exec::Sim, RenderData>(
     // Render once to get the ball rolling.
     display.display(&sim, &cfg, init_disp_data)?;
     loop {
-        let action = input.next_action()?;
-        log::debug!("Executing action {:?}", action);
-        match action {
-            uial::input::SystemAction::Exit => {
+        let event = events.next_event(sim.next_deadline())?;
+        match &event {
+            // Timers can fire many times a second, such as while text is being revealed.
+            uial::event::Event::Timer => log::trace!("Handling event {:?}", event),
+            _ => log::debug!("Handling event {:?}", event),
+        }
+        let render_mode = match event {
+            uial::event::Event::Input(uial::input::SystemAction::Exit) => {
                 log::info!("System exit command received. Shutting down.");
+                sim.on_exit(&cfg);
                 break;
             }
-            uial::input::SystemAction::Action(a) => match sim.resolve(&cfg, a)? {
-                uial::display::RenderMode::Render(data) => {
-                    log::trace!("Render requested.");
-                    display.display(&sim, &cfg, data)?
-                },
-                uial::display::RenderMode::Ignore => (),
-            },
+            uial::event::Event::Input(uial::input::SystemAction::Menu) => sim.open_menu(&cfg),
+            uial::event::Event::Input(uial::input::SystemAction::Action(a)) => sim.resolve(&cfg, a),
+            uial::event::Event::Input(uial::input::SystemAction::Invalid(msg)) => sim.on_invalid_input(&cfg, msg),
+            uial::event::Event::Timer => sim.on_timer(&cfg),
+            uial::event::Event::FilesChanged(paths) => sim.on_files_changed(&cfg, paths.as_slice()),
         };
+        match render_mode.or_else(|e| {
+            log::warn!("Resolution failed with {:?}. Attempting to recover.", e);
+            sim.recover(&cfg, e)
+        })? {
+            uial::display::RenderMode::Render(data) => {
+                log::trace!("Render requested.");
+                display.display(&sim, &cfg, data)?
+            },
+            uial::display::RenderMode::Ignore => (),
+        }
+        if sim.wants_exit() {
+            log::info!("Sim asked to exit. Shutting down.");
+            sim.on_exit(&cfg);
+            break;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,53 @@
+use super::{Record, Store};
+use crate::{exec, game::{self, KeyedStore}};
+
+/// Achievements unlocked across all playthroughs. Kept in the saves directory.
+pub struct Achievements {
+    unlocked: Record<String>,
+    /// Unlocked, but not announced yet.
+    announce: Vec<String>,
+}
+
+impl Achievements {
+    pub fn load(cfg: &game::Cfg<Store>) -> Self {
+        Self {
+            unlocked: Record::load(cfg, "achievements.yaml"),
+            announce: vec![],
+        }
+    }
+
+    pub fn is_unlocked(&self, id: &str) -> bool {
+        self.unlocked.contains(id)
+    }
+
+    /// Unlocks everything `unlocks` names, and everything whose condition now holds.
+    pub(super) fn update(&mut self, cfg: &game::Cfg<Store>, store: &Store, unlocks: &[String]) -> Result<(), exec::ResolutionErr> {
+        if let Some(id) = unlocks.iter().find(|id| !cfg.achievements.contains_key(id.as_str())) {
+            return Err(exec::ResolutionErr::BadContent(format!(
+                "Achievement {:?} isn't defined in the game cfg.",
+                id,
+            )));
+        }
+        let met: Vec<_> = cfg
+            .achievements
+            .iter()
+            .filter(|(id, achievement)| {
+                !self.unlocked.contains(id.as_str())
+                    && achievement.condition.as_ref().is_some_and(|guard| store.check_guard(guard))
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in unlocks.iter().cloned().chain(met) {
+            if self.unlocked.insert(id.clone()) {
+                log::info!("Unlocked achievement {}.", id);
+                self.announce.push(id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Ids unlocked since the last call.
+    pub(super) fn take_announcements(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.announce)
+    }
+}
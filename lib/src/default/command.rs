@@ -0,0 +1,145 @@
+//! Commands typed in instead of playing on, such as `save 2`.
+use super::{menu::Screen, Action, DisplayData, Sim, Store};
+use crate::{exec, game, uial::display::RenderMode};
+
+const HELP: &str = "\
+Commands:
+  (enter)       Go on.
+  N             Pick choice N.
+  >>            Skip past lines seen before.
+  back          Undo the last thing done.
+  save N        Save to slot N.
+  load N        Load slot N.
+  log           Read back over the lines so far.
+  inventory     See what you're carrying.
+  achievements  See which achievements are unlocked.
+  menu          Open the system menu.
+  quit          Quit.";
+
+/// Listed after `HELP` in debug builds, which are the only ones that have them.
+const DEBUG_HELP: &str = "
+  stats         See every stat and item.
+  :COMMAND      Use the debug console. Enter `:help` to see what it can do.";
+
+impl Action {
+    /// `save N` and `load N`.
+    pub(super) fn parse_slot_command(cmd: &str) -> Option<Self> {
+        let mut words = cmd.split_whitespace();
+        let command = match words.next()? {
+            "save" => Action::Save,
+            "load" => Action::Load,
+            _ => return None,
+        };
+        let slot = words.next()?.parse().ok()?;
+        if words.next().is_some() {
+            return None;
+        }
+        Some(command(slot))
+    }
+
+    /// What was typed in for a command, for when it answers a prompt instead.
+    pub(super) fn command_text(&self) -> Option<String> {
+        let text = match self {
+            Action::Inventory => "inventory".to_owned(),
+            Action::Achievements => "achievements".to_owned(),
+            Action::Help => "help".to_owned(),
+            Action::Back => "back".to_owned(),
+            Action::Save(slot) => format!("save {}", slot),
+            Action::Load(slot) => format!("load {}", slot),
+            Action::Log => "log".to_owned(),
+            Action::Quit => "quit".to_owned(),
+            Action::Stats => "stats".to_owned(),
+            Action::Debug(input) => format!(":{}", input),
+            Action::Select(_) | Action::Progress | Action::Skip | Action::Entry(_) | Action::PromptRetry => return None,
+        };
+        Some(text)
+    }
+}
+
+fn render_notice(text: String) -> Option<RenderMode<DisplayData>> {
+    Some(RenderMode::Render(DisplayData {
+        notice: Some(text),
+        ..Default::default()
+    }))
+}
+
+fn render_error(text: String) -> Option<RenderMode<DisplayData>> {
+    Some(RenderMode::Render(DisplayData {
+        error_text: Some(text),
+        ..Default::default()
+    }))
+}
+
+impl Sim {
+    /// Carries out `a` if it's a command, or reports it if it's an unknown one. Anything else is
+    /// left to the story.
+    pub(super) fn resolve_command(
+        &mut self,
+        cfg: &game::Cfg<Store>,
+        a: &Action,
+    ) -> Result<Option<RenderMode<DisplayData>>, exec::ResolutionErr> {
+        let render_mode = match a {
+            Action::Help if cfg!(debug_assertions) => render_notice(format!("{}{}", HELP, DEBUG_HELP)),
+            Action::Help => render_notice(HELP.to_owned()),
+            Action::Back => {
+                if self.restore_checkpoint() {
+                    log::info!("Went back to {}:{}.", self.scene.name(), self.curr_line);
+                    Some(RenderMode::Render(DisplayData::default()))
+                } else {
+                    render_error("There's nothing to go back to.".to_owned())
+                }
+            }
+            Action::Save(_) if self.ending.is_some() => render_error("The story's over, so there's nothing to save.".to_owned()),
+            Action::Save(slot) => match self.save_slot(*slot) {
+                Ok(notice) => render_notice(notice),
+                Err(text) => render_error(text),
+            },
+            Action::Load(slot) => Some(self.load_slot(cfg, *slot)?),
+            Action::Log => {
+                self.pause(Screen::Backlog);
+                Some(RenderMode::Render(DisplayData::default()))
+            }
+            Action::Quit => Some(self.quit(cfg)),
+            Action::Stats => render_notice(self.stats()),
+            Action::Entry(text) => {
+                let word = text.split_whitespace().next().unwrap_or_default();
+                if word == "save" || word == "load" {
+                    render_error(format!("`{}` needs a slot number, like `{} 1`.", word, word))
+                } else {
+                    render_error(format!("Unknown command `{}`. Enter `help` to see what can be done.", text))
+                }
+            }
+            Action::Select(_)
+            | Action::Progress
+            | Action::Skip
+            | Action::PromptRetry
+            | Action::Inventory
+            | Action::Achievements
+            | Action::Debug(_) => None,
+        };
+        Ok(render_mode)
+    }
+
+    /// Everything in the store, sorted by name.
+    pub(super) fn stats(&self) -> String {
+        let mut stats: Vec<_> = self
+            .store
+            .bools
+            .iter()
+            .map(|(name, b)| format!("  {} = {}", name, b))
+            .chain(self.store.ints.iter().map(|(name, i)| format!("  {} = {}", name, i)))
+            .chain(self.store.nums.iter().map(|(name, n)| format!("  {} = {}", name, n)))
+            .chain(self.store.strings.iter().map(|(name, s)| format!("  {} = {:?}", name, s)))
+            .collect();
+        stats.sort();
+        let mut items: Vec<_> = self
+            .store
+            .items
+            .iter()
+            .map(|(item, count)| format!("  {} x{}", item, count))
+            .collect();
+        items.sort();
+        let list = |lines: Vec<String>| if lines.is_empty() { "  (none)".to_owned() } else { lines.join("\n") };
+        format!("Stats:\n{}\nItems:\n{}", list(stats), list(items))
+    }
+}
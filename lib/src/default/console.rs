@@ -0,0 +1,234 @@
+//! The debug console, for poking at the story while it runs. Debug builds only.
+use super::{error_text, DisplayData, LoadedScene, Sim, Store};
+use crate::{
+    exec,
+    game::{self, guard::{Guard, KeyedGuard, ValueGuard}, KeyedStore},
+    uial::display::RenderMode,
+};
+use std::ops::Bound;
+
+const HELP: &str = "\
+Console commands:
+  :store               Show everything in the store.
+  :set CHANGE          Change the store, like in scripts, e.g. `:set gold += 5` or `:set give key`.
+  :jump SCENE[:LINE]   Go to a line, counting from 0.
+  :eval GUARD          Check a guard against the store, e.g. `:eval gold >= 5 && !angry`.
+  :choices             List the current line's choices, and why any hidden ones are.";
+
+fn render(text: String) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+    Ok(RenderMode::Render(DisplayData {
+        notice: Some(text),
+        ..Default::default()
+    }))
+}
+
+fn render_error(text: String) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+    Ok(RenderMode::Render(DisplayData {
+        error_text: Some(text),
+        ..Default::default()
+    }))
+}
+
+/// Writes `lo <= name <= hi` the way a guard would be written in a script.
+fn describe_bounds<N: std::fmt::Display + PartialEq>(name: &str, (lo, hi): &(Bound<N>, Bound<N>)) -> String {
+    if let (Bound::Included(lo), Bound::Included(hi)) = (lo, hi) {
+        if lo == hi {
+            return format!("{} == {}", name, lo);
+        }
+    }
+    let lo = match lo {
+        Bound::Included(lo) => Some(format!("{} >= {}", name, lo)),
+        Bound::Excluded(lo) => Some(format!("{} > {}", name, lo)),
+        Bound::Unbounded => None,
+    };
+    let hi = match hi {
+        Bound::Included(hi) => Some(format!("{} <= {}", name, hi)),
+        Bound::Excluded(hi) => Some(format!("{} < {}", name, hi)),
+        Bound::Unbounded => None,
+    };
+    match (lo, hi) {
+        (Some(lo), Some(hi)) => format!("{} && {}", lo, hi),
+        (Some(bound), None) | (None, Some(bound)) => bound,
+        (None, None) => format!("{} is set", name),
+    }
+}
+
+/// Writes `guard` back out in script syntax.
+fn describe(guard: &Guard<Store>) -> String {
+    let nested = |guard: &Guard<Store>| match guard {
+        Guard::And(_) | Guard::Or(_) => format!("({})", describe(guard)),
+        _ => describe(guard),
+    };
+    match guard {
+        Guard::Value(g) => match &g.value {
+            ValueGuard::Bool(true) => g.name.clone(),
+            ValueGuard::Bool(false) => format!("{} == false", g.name),
+            ValueGuard::Int(bounds) => describe_bounds(&g.name, bounds),
+            ValueGuard::Num(bounds) => describe_bounds(&g.name, bounds),
+            ValueGuard::Text(text) => format!("{} == {:?}", g.name, text),
+        },
+        Guard::Item(g) if g.count == (Bound::Included(1), Bound::Unbounded) => format!("has {}", g.item),
+        Guard::Item(g) => describe_bounds(&format!("has {}", g.item), &g.count),
+        Guard::Not(g) => match g.as_ref() {
+            Guard::Value(KeyedGuard { value: ValueGuard::Bool(true), .. }) => format!("!{}", describe(g)),
+            Guard::Item(item) if item.count == (Bound::Included(1), Bound::Unbounded) => format!("!{}", describe(g)),
+            Guard::Value(_) | Guard::Item(_) => format!("!({})", describe(g)),
+            _ => format!("!{}", nested(g)),
+        },
+        Guard::And(gg) => gg.iter().map(nested).collect::<Vec<_>>().join(" && "),
+        Guard::Or(gg) => gg.iter().map(nested).collect::<Vec<_>>().join(" || "),
+    }
+}
+
+impl Store {
+    /// What `name` holds, for explaining guards.
+    fn describe_value(&self, name: &str) -> String {
+        if let Some(b) = self.bools.get(name) {
+            format!("{} is {}", name, b)
+        } else if let Some(i) = self.ints.get(name) {
+            format!("{} is {}", name, i)
+        } else if let Some(n) = self.nums.get(name) {
+            format!("{} is {}", name, n)
+        } else if let Some(s) = self.strings.get(name) {
+            format!("{} is {:?}", name, s)
+        } else {
+            format!("{} isn't set", name)
+        }
+    }
+
+    /// The parts of `guard` that fail, each with why. Empty if it passes.
+    fn explain_failure(&self, guard: &Guard<Store>) -> Vec<String> {
+        if self.check_guard(guard) {
+            return vec![];
+        }
+        match guard {
+            Guard::Value(g) => vec![format!("`{}` fails, as {}", describe(guard), self.describe_value(&g.name))],
+            Guard::Item(g) => vec![format!(
+                "`{}` fails, as the player has {} {}",
+                describe(guard),
+                self.item_count(&g.item),
+                g.item,
+            )],
+            Guard::Not(g) => vec![format!("`{}` fails, as `{}` passes", describe(guard), describe(g))],
+            Guard::And(gg) | Guard::Or(gg) => gg.iter().flat_map(|g| self.explain_failure(g)).collect(),
+        }
+    }
+}
+
+impl Sim {
+    /// Carries out a console command, given without its leading `:`.
+    pub(super) fn resolve_console(&mut self, cfg: &game::Cfg<Store>, input: &str) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+        let input = input.trim();
+        let (command, rest) = match input.find(char::is_whitespace) {
+            Some(idx) => (&input[..idx], input[idx..].trim()),
+            None => (input, ""),
+        };
+        log::debug!("Console command {:?} with {:?}.", command, rest);
+        match command {
+            "" | "help" => render(HELP.to_owned()),
+            "store" => render(self.stats()),
+            "set" => self.console_set(cfg, rest),
+            "jump" => self.console_jump(cfg, rest),
+            "eval" => match game::script::parse_guard::<Store>(rest) {
+                Ok(guard) => {
+                    let reasons = self.store.explain_failure(&guard);
+                    if reasons.is_empty() {
+                        render(format!("`{}` passes.", describe(&guard)))
+                    } else {
+                        render(format!("`{}` fails:\n  {}", describe(&guard), reasons.join("\n  ")))
+                    }
+                }
+                Err(e) => render_error(format!("Couldn't read the guard: {}", e.msg)),
+            },
+            "choices" => self.console_choices(cfg),
+            _ => render_error(format!("Unknown console command `:{}`. Enter `:help` to see what can be done.", command)),
+        }
+    }
+
+    fn console_set(&mut self, cfg: &game::Cfg<Store>, change: &str) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+        if change.is_empty() {
+            return render_error("`:set` needs a change, like `:set gold += 5`.".to_owned());
+        }
+        let trigger = match game::script::parse_trigger::<Store>(&format!("~ {}", change)) {
+            Ok(trigger) => trigger,
+            Err(e) => return render_error(format!("Couldn't read the change: {}", e.msg)),
+        };
+        self.save_checkpoint();
+        let jump = Self::apply_trigger(cfg, &self.meters, &mut self.store, &mut self.achievements, &trigger);
+        self.hooks.note_changes(&self.store);
+        match jump {
+            Ok(None) => render(format!("Applied `{}`.", change)),
+            Ok(Some(jump)) => {
+                // A meter crossed a threshold that moves the story on.
+                self.follow(cfg, jump)?;
+                self.progress(cfg)?;
+                self.enter_line(cfg)?;
+                render(format!("Applied `{}`, which moved the story to {}:{}.", change, self.scene.name(), self.curr_line))
+            }
+            Err(e) => {
+                self.restore_checkpoint();
+                render_error(error_text(&e))
+            }
+        }
+    }
+
+    fn console_jump(&mut self, cfg: &game::Cfg<Store>, target: &str) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+        let (name, line) = match target.find(':') {
+            Some(idx) => match target[idx + 1..].trim().parse::<usize>() {
+                Ok(line) => (target[..idx].trim(), line),
+                Err(_) => return render_error(format!("{:?} isn't a line number.", &target[idx + 1..])),
+            },
+            None => (target, 0),
+        };
+        if name.is_empty() {
+            return render_error("`:jump` needs a scene, like `:jump intro:3`.".to_owned());
+        }
+        let scene = LoadedScene::new(name.to_owned());
+        let line_count = match scene.get_or_load(cfg) {
+            Ok((_guard, loaded)) => loaded.lines.len(),
+            Err(e) => return render_error(error_text(&e)),
+        };
+        if line >= line_count {
+            return render_error(format!("Scene {} only has lines 0 to {}.", name, line_count.saturating_sub(1)));
+        }
+        self.save_checkpoint();
+        self.jump_to_scene(scene, Some(line));
+        self.ending = None;
+        if let Err(e) = self.progress(cfg).and_then(|_| self.enter_line(cfg)) {
+            self.restore_checkpoint();
+            return render_error(error_text(&e));
+        }
+        log::info!("Jumped to {}:{} from the console.", self.scene.name(), self.curr_line);
+        render(format!("Jumped to {}:{}.", self.scene.name(), self.curr_line))
+    }
+
+    fn console_choices(&mut self, cfg: &game::Cfg<Store>) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+        if self.ending.is_some() {
+            return render_error("The story's over, so there are no choices.".to_owned());
+        }
+        let (_guard, scene) = self.scene.get_or_load(cfg)?;
+        let choices = match scene.lines.get(self.curr_line).and_then(|l| l.to_inner().line()).and_then(|l| l.choices.as_ref()) {
+            Some(choices) => choices,
+            None => return render_error(format!("There are no choices at {}:{}.", self.scene.name(), self.curr_line)),
+        };
+        let mut visible = vec![];
+        let mut hidden = vec![];
+        for (choice_num, choice) in choices.choices.iter().enumerate() {
+            let default = if choice_num == choices.default_choice { " (default choice)" } else { "" };
+            let reasons = choice.guard.as_ref().map_or_else(Vec::new, |guard| self.store.explain_failure(guard));
+            if reasons.is_empty() {
+                visible.push(format!("  {}) {}{}", visible.len() + 1, self.strings.choice(choice), default));
+            } else {
+                hidden.push(format!("  {}{}\n    {}", self.strings.choice(choice), default, reasons.join("\n    ")));
+            }
+        }
+        let list = |lines: Vec<String>| if lines.is_empty() { "  (none)".to_owned() } else { lines.join("\n") };
+        render(format!(
+            "Choices at {}:{}:\nVisible:\n{}\nHidden:\n{}",
+            self.scene.name(),
+            self.curr_line,
+            list(visible),
+            list(hidden),
+        ))
+    }
+}
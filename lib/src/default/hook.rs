@@ -0,0 +1,104 @@
+use super::Store;
+use std::collections::{BTreeSet, HashMap};
+
+/// Something that happened in the story, for hooks to react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    LineShown { scene: String, line: usize, speaker: Option<String>, text: String },
+    /// `by_player` is false when the choice was picked for them, by default or because time ran out.
+    ChoiceMade { scene: String, line: usize, text: String, by_player: bool },
+    /// A value is `None` when the stat wasn't set.
+    StatChanged { name: String, old: Option<Value>, new: Option<Value> },
+    ItemsChanged { item: String, old: u32, new: u32 },
+    SceneEntered { scene: String },
+    SceneExited { scene: String },
+    /// The story reached `ending`, or the player quit if there's none.
+    GameEnded { ending: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Num(f64),
+    Text(String),
+}
+
+pub type Callback = Box<dyn FnMut(&Event) + Send>;
+
+/// Callbacks run, in the order they were added, whenever something happens in the story.
+#[derive(Default)]
+pub struct Hooks {
+    callbacks: Vec<Callback>,
+    /// The store as of the last reported change, to tell what changed since.
+    last_store: Option<Store>,
+}
+
+impl Hooks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on(mut self, callback: impl FnMut(&Event) + Send + 'static) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    pub(super) fn emit(&mut self, event: Event) {
+        for callback in self.callbacks.iter_mut() {
+            callback(&event);
+        }
+    }
+
+    /// Takes `store` as where things stand, without reporting anything.
+    pub(super) fn reset(&mut self, store: &Store) {
+        if !self.callbacks.is_empty() {
+            self.last_store = Some(store.clone());
+        }
+    }
+
+    /// Reports every stat and item that changed since the last call.
+    pub(super) fn note_changes(&mut self, store: &Store) {
+        if self.callbacks.is_empty() {
+            return;
+        }
+        let last = match self.last_store.replace(store.clone()) {
+            Some(last) => last,
+            // Nothing to compare against the first time around.
+            None => return,
+        };
+        fn diff<V: Clone + PartialEq>(
+            old: &HashMap<String, V>,
+            new: &HashMap<String, V>,
+            wrap: impl Fn(V) -> Value,
+            out: &mut Vec<Event>,
+        ) {
+            let names: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+            for name in names {
+                let (old, new) = (old.get(name), new.get(name));
+                if old != new {
+                    out.push(Event::StatChanged {
+                        name: name.clone(),
+                        old: old.cloned().map(&wrap),
+                        new: new.cloned().map(&wrap),
+                    });
+                }
+            }
+        }
+        let mut events = vec![];
+        diff(&last.bools, &store.bools, Value::Bool, &mut events);
+        diff(&last.ints, &store.ints, Value::Int, &mut events);
+        diff(&last.nums, &store.nums, Value::Num, &mut events);
+        diff(&last.strings, &store.strings, Value::Text, &mut events);
+        let items: BTreeSet<_> = last.items.keys().chain(store.items.keys()).collect();
+        for item in items {
+            let (old, new) = (last.items.get(item).copied().unwrap_or(0), store.items.get(item).copied().unwrap_or(0));
+            if old != new {
+                events.push(Event::ItemsChanged { item: item.clone(), old, new });
+            }
+        }
+        for event in events {
+            self.emit(event);
+        }
+    }
+}
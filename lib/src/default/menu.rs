@@ -0,0 +1,315 @@
+//! Screens shown instead of the story, such as the title screen.
+use super::{error_text, hook, settings, Action, DisplayData, RenderTup, Save, Sim, Store};
+use crate::{exec, game::{self, menu::TitleEntry}, uial::display::RenderMode};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Screen {
+    Title,
+    /// The system menu, with the story paused underneath.
+    Pause,
+    Save,
+    Load,
+    /// Editable, and saved as soon as anything changes.
+    Settings,
+    /// Endings and achievements reached so far.
+    Gallery,
+    /// Lines shown so far.
+    Backlog,
+}
+
+impl Screen {
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Screen::Title => "title",
+            Screen::Pause => "pause",
+            Screen::Save => "save",
+            Screen::Load => "load",
+            Screen::Settings => "settings",
+            Screen::Gallery => "gallery",
+            Screen::Backlog => "backlog",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PauseEntry {
+    Resume,
+    Save,
+    Load,
+    Settings,
+    Backlog,
+    Quit,
+}
+
+impl PauseEntry {
+    const ALL: [Self; 6] = [
+        PauseEntry::Resume,
+        PauseEntry::Save,
+        PauseEntry::Load,
+        PauseEntry::Settings,
+        PauseEntry::Backlog,
+        PauseEntry::Quit,
+    ];
+
+    fn label(self, cfg: &game::Cfg<Store>) -> &'static str {
+        match self {
+            PauseEntry::Resume => "Resume",
+            PauseEntry::Save => "Save",
+            PauseEntry::Load => "Load",
+            PauseEntry::Settings => "Settings",
+            PauseEntry::Backlog => "Backlog",
+            PauseEntry::Quit if cfg.title_screen.is_some() => "Quit to title",
+            PauseEntry::Quit => "Quit",
+        }
+    }
+}
+
+fn render_error(text: String) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+    Ok(RenderMode::Render(DisplayData {
+        error_text: Some(text),
+        ..Default::default()
+    }))
+}
+
+impl Sim {
+    /// Title entries that can be picked right now, in order.
+    fn title_entries(&self, cfg: &game::Cfg<Store>) -> Vec<TitleEntry> {
+        let has_save = self.slots.latest().is_some();
+        cfg.title_screen
+            .iter()
+            .flat_map(|title| title.entries.iter().copied())
+            .filter(|entry| *entry != TitleEntry::Continue || has_save)
+            .collect()
+    }
+
+    /// Opens `screen` over the story, pausing it if it isn't already.
+    pub(super) fn pause(&mut self, screen: Screen) {
+        if self.menus.is_empty() {
+            // Time spent on the overlay counts as paused too.
+            self.paused_at = Some(self.overlay.take().map_or_else(Instant::now, |(_, opened_at)| opened_at));
+        }
+        self.menus.push(screen);
+    }
+
+    /// Goes back to the screen before, and resumes the story if that was the last menu.
+    pub(super) fn close_menu(&mut self) {
+        self.menus.pop();
+        if self.menus.is_empty() {
+            if let Some(paused_at) = self.paused_at.take() {
+                self.shift_deadlines(paused_at.elapsed());
+            }
+        }
+    }
+
+    /// Saves the player's change to `entry`, which now takes precedence over this session's.
+    fn change_setting(&mut self, cfg: &game::Cfg<Store>, entry: settings::Entry) {
+        entry.clear(&mut self.overrides);
+        self.settings.save(cfg);
+        if let (settings::Entry::LogLevel, Some(level)) = (entry, self.settings.log_level) {
+            log::set_max_level(level.level_filter());
+        }
+    }
+
+    /// Saves where the player is to `slot`. Returns what to tell them either way.
+    pub(super) fn save_slot(&mut self, slot: usize) -> Result<String, String> {
+        let save = Save::new(self.scene.name().to_owned(), self.curr_line, self.store.clone());
+        self.slots.save(slot, &save)?;
+        log::info!("Saved {}:{} to slot {}.", save.scene, save.line, slot);
+        Ok(format!("Saved to slot {}.", slot))
+    }
+
+    /// Goes back to the title screen if there is one, or ends the session otherwise.
+    pub(super) fn quit(&mut self, cfg: &game::Cfg<Store>) -> RenderMode<DisplayData> {
+        if cfg.title_screen.is_none() {
+            self.exit_requested = true;
+            return RenderMode::Ignore;
+        }
+        if self.ending.is_none() {
+            self.hooks.emit(hook::Event::GameEnded { ending: None });
+        }
+        self.menus = vec![Screen::Title];
+        self.paused_at = None;
+        RenderMode::Render(DisplayData::default())
+    }
+
+    /// If the save can't be continued from, goes back to the title screen, or gives up without one.
+    pub(super) fn load_slot(&mut self, cfg: &game::Cfg<Store>, slot: usize) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+        let save = match self.slots.load(slot) {
+            Ok(save) => save,
+            Err(text) => return render_error(text),
+        };
+        if let Err(e) = self.load_save(cfg, save) {
+            if cfg.title_screen.is_none() {
+                return Err(e);
+            }
+            self.menus = vec![Screen::Title];
+            return render_error(error_text(&e));
+        }
+        Ok(RenderMode::Render(DisplayData::default()))
+    }
+
+    pub(super) fn resolve_menu(
+        &mut self,
+        cfg: &game::Cfg<Store>,
+        screen: Screen,
+        a: Action,
+    ) -> Result<RenderMode<DisplayData>, exec::ResolutionErr> {
+        match (screen, a) {
+            (Screen::Title, Action::Select(n)) => {
+                let entry = match self.title_entries(cfg).get(n) {
+                    Some(entry) => *entry,
+                    None => return render_error(format!("There's no entry {} here.", n + 1)),
+                };
+                match entry {
+                    TitleEntry::NewGame => self.new_game(cfg)?,
+                    TitleEntry::Continue => match self.slots.latest() {
+                        Some(slot) => return self.load_slot(cfg, slot),
+                        None => return render_error("There's nothing to continue.".to_owned()),
+                    },
+                    TitleEntry::Load => self.menus.push(Screen::Load),
+                    TitleEntry::Settings => self.menus.push(Screen::Settings),
+                    TitleEntry::Gallery => self.menus.push(Screen::Gallery),
+                    TitleEntry::Quit => {
+                        self.exit_requested = true;
+                        return Ok(RenderMode::Ignore);
+                    }
+                }
+            }
+            (Screen::Title, Action::Progress) => {}
+            (Screen::Title, _) => return render_error("Pick one of the entries by its number.".to_owned()),
+            (Screen::Pause, Action::Select(n)) => match PauseEntry::ALL.get(n) {
+                Some(PauseEntry::Resume) => self.close_menu(),
+                Some(PauseEntry::Save) => self.menus.push(Screen::Save),
+                Some(PauseEntry::Load) => self.menus.push(Screen::Load),
+                Some(PauseEntry::Settings) => self.menus.push(Screen::Settings),
+                Some(PauseEntry::Backlog) => self.menus.push(Screen::Backlog),
+                Some(PauseEntry::Quit) => return Ok(self.quit(cfg)),
+                None => return render_error(format!("There's no entry {} here.", n + 1)),
+            },
+            (Screen::Pause, Action::Progress | Action::PromptRetry) => self.close_menu(),
+            (Screen::Pause, _) => return render_error("Pick one of the entries by its number, or 0 to resume.".to_owned()),
+            (Screen::Save, Action::Select(n)) => {
+                let notice = match self.save_slot(n + 1) {
+                    Ok(notice) => notice,
+                    Err(text) => return render_error(text),
+                };
+                self.close_menu();
+                return Ok(RenderMode::Render(DisplayData {
+                    notice: Some(notice),
+                    ..Default::default()
+                }));
+            }
+            (Screen::Load, Action::Select(n)) => return self.load_slot(cfg, n + 1),
+            (Screen::Save | Screen::Load, Action::Progress | Action::PromptRetry) => self.close_menu(),
+            (Screen::Save | Screen::Load, _) => return render_error("Pick a slot by its number, or 0 to go back.".to_owned()),
+            (Screen::Settings, Action::Select(n)) => match settings::Entry::ALL.get(n) {
+                Some(entry) => {
+                    let current = self.settings();
+                    entry.cycle(cfg, &current, &mut self.settings);
+                    self.change_setting(cfg, *entry);
+                }
+                None => return render_error(format!("There's no setting {} here.", n + 1)),
+            },
+            // Such as `1 45`, to set the text speed to exactly 45.
+            (Screen::Settings, Action::Entry(text)) => {
+                let mut words = text.split_whitespace();
+                let entry = words
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .and_then(|n| n.checked_sub(1))
+                    .and_then(|n| settings::Entry::ALL.get(n).copied());
+                let (entry, value) = match (entry, words.next(), words.next()) {
+                    (Some(entry), Some(value), None) => (entry, value),
+                    _ => return render_error("Pick a setting by its number, optionally followed by what to set it to.".to_owned()),
+                };
+                if let Err(text) = entry.set(&mut self.settings, value) {
+                    return render_error(text);
+                }
+                self.change_setting(cfg, entry);
+            }
+            (Screen::Settings | Screen::Gallery | Screen::Backlog, _) => self.close_menu(),
+        }
+        Ok(RenderMode::Render(DisplayData::default()))
+    }
+}
+
+impl<'a> RenderTup<'a, Sim> {
+    pub(super) fn fmt_menu(&self, f: &mut std::fmt::Formatter, screen: Screen) -> std::fmt::Result {
+        let RenderTup(sim, cfg, _) = self;
+        match screen {
+            Screen::Title => {
+                if let Some(title) = cfg.title_screen.as_ref() {
+                    if let Some(text) = title.title.as_ref() {
+                        writeln!(f, "{}", text)?;
+                    }
+                    if let Some(text) = title.subtitle.as_ref() {
+                        writeln!(f, "{}", text)?;
+                    }
+                    for (entry, num) in sim.title_entries(cfg).into_iter().zip(1..) {
+                        writeln!(f, "{}) {}", num, title.label(entry))?;
+                    }
+                }
+                Ok(())
+            }
+            Screen::Pause => {
+                writeln!(f, "Paused.")?;
+                for (entry, num) in PauseEntry::ALL.iter().zip(1..) {
+                    writeln!(f, "{}) {}", num, entry.label(cfg))?;
+                }
+                Ok(())
+            }
+            Screen::Save | Screen::Load => {
+                writeln!(f, "{} which slot?", if screen == Screen::Save { "Save to" } else { "Load" })?;
+                for (save, slot) in sim.slots.list().into_iter().zip(1..) {
+                    match save {
+                        Some(save) => writeln!(f, "{}) {}, line {}, saved {}", slot, save.scene, save.line, save.saved_at_local())?,
+                        None => writeln!(f, "{}) Empty", slot)?,
+                    }
+                }
+                writeln!(f, "0) Back")
+            }
+            Screen::Settings => {
+                writeln!(f, "Settings:")?;
+                let current = sim.settings();
+                for (entry, num) in settings::Entry::ALL.iter().zip(1..) {
+                    writeln!(f, "{}) {}", num, entry.describe(cfg, &current))?;
+                }
+                writeln!(f, "(Pick a setting to change it, or follow its number with a value, like `1 45`. Press enter to go back.)")
+            }
+            Screen::Gallery => {
+                let reached = cfg.endings.keys().filter(|id| sim.endings.contains(id.as_str())).count();
+                writeln!(f, "Endings ({}/{}):", reached, cfg.endings.len())?;
+                for (id, ending) in cfg.endings.iter() {
+                    if sim.endings.contains(id) {
+                        writeln!(f, "  [x] {}", ending.title)?;
+                    } else {
+                        writeln!(f, "  [ ] ???")?;
+                    }
+                }
+                let unlocked = cfg.achievements.keys().filter(|id| sim.achievements.is_unlocked(id)).count();
+                writeln!(f, "Achievements ({}/{}):", unlocked, cfg.achievements.len())?;
+                for (id, achievement) in cfg.achievements.iter() {
+                    let mark = if sim.achievements.is_unlocked(id) { 'x' } else { ' ' };
+                    writeln!(f, "  [{}] {}", mark, achievement.title)?;
+                }
+                writeln!(f, "(Press enter to go back.)")
+            }
+            Screen::Backlog => {
+                writeln!(f, "Backlog:")?;
+                for (speaker, text) in sim.backlog.iter() {
+                    match speaker {
+                        Some(speaker) => {
+                            let name = game::character::find(&cfg.characters, speaker)
+                                .map_or(speaker.as_str(), |(_, character)| character.name.as_str());
+                            let name = sim.strings.translate(None, name);
+                            writeln!(f, "  {}: {}", name, text)?;
+                        }
+                        None => writeln!(f, "  {}", text)?,
+                    }
+                }
+                writeln!(f, "(Press enter to go back.)")
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Renders as JSON, one object per line, for frontends driving the game instead of a player.
+use super::{DisplayData, Overlay, RenderTup, Sim, Store};
+use crate::{exec, game::{self, KeyedStore}, uial};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+struct Choice<'a> {
+    /// What to `select` to pick it.
+    index: usize,
+    text: &'a str,
+}
+
+/// Everything on screen. Keys are always there, and `null` or empty if they don't apply.
+#[derive(Serialize, Debug)]
+struct Event<'a> {
+    scene: &'a str,
+    line: usize,
+    /// The speaker as the script names them.
+    speaker_id: Option<&'a str>,
+    /// The speaker as they're shown, such as a character's name.
+    speaker: Option<&'a str>,
+    text: Option<&'a str>,
+    /// How many characters of `text` are revealed so far, if not all of them.
+    shown: Option<usize>,
+    /// Whether the line asks for text, to be answered with `entry`.
+    prompt: bool,
+    /// Only the visible ones, and only once the text is all there.
+    choices: Vec<Choice<'a>>,
+    /// The index of the choice picked by `progress`, or when time runs out.
+    default_choice: Option<usize>,
+    time_left_ms: Option<u64>,
+    /// What's shown instead of the story, if anything, such as `pause` or `inventory`.
+    screen: Option<&'static str>,
+    /// `screen` as it's shown to players in text mode.
+    screen_text: Option<String>,
+    ending: Option<&'a str>,
+    error: Option<&'a str>,
+    notice: Option<&'a str>,
+    /// Ids of achievements that were just unlocked.
+    unlocked: &'a [String],
+}
+
+pub struct JsonTup<'a, Sim: exec::Sim>(RenderTup<'a, Sim>);
+
+/// Renders each frame as a JSON object on its own line.
+pub struct JsonRender;
+
+impl<'a> uial::display::RenderGroup<'a, Sim, game::Cfg<Store>, DisplayData> for JsonRender {
+    type Output = JsonTup<'a, Sim>;
+    fn create(a: &'a Sim, b: &'a game::Cfg<Store>, c: DisplayData) -> Self::Output {
+        JsonTup(RenderTup(a, b, c))
+    }
+}
+
+impl<'a> JsonTup<'a, Sim> {
+    fn event(&self) -> Event<'_> {
+        let RenderTup(sim, cfg, data) = &self.0;
+        let screen = match (sim.menus.last(), sim.overlay.as_ref(), sim.ending.as_ref()) {
+            (Some(screen), _, _) => Some(screen.name()),
+            (None, Some((Overlay::Inventory, _)), _) => Some("inventory"),
+            (None, Some((Overlay::Achievements, _)), _) => Some("achievements"),
+            (None, None, Some(_)) => Some("ending"),
+            (None, None, None) => None,
+        };
+        let mut event = Event {
+            scene: sim.scene.name(),
+            line: sim.curr_line,
+            speaker_id: None,
+            speaker: None,
+            text: None,
+            shown: None,
+            prompt: false,
+            choices: vec![],
+            default_choice: None,
+            time_left_ms: None,
+            screen,
+            screen_text: screen.map(|_| RenderTup(*sim, *cfg, DisplayData::default()).to_string()),
+            ending: sim.ending.as_deref(),
+            error: data.error_text.as_deref(),
+            notice: data.notice.as_deref(),
+            unlocked: data.unlocked.as_slice(),
+        };
+        if screen.is_some() {
+            return event;
+        }
+        let line = sim
+            .scene
+            .get_or_load(cfg)
+            .ok()
+            .and_then(|(_guard, scene)| scene.lines.get(sim.curr_line))
+            .and_then(|line| line.to_inner().line());
+        let line = match line {
+            Some(line) => line,
+            None => return event,
+        };
+        if let Some(speaker) = line.speaker.as_ref() {
+            let name = game::character::find(&cfg.characters, speaker)
+                .map_or(speaker.as_str(), |(_, character)| character.name.as_str());
+            event.speaker_id = Some(speaker);
+            event.speaker = Some(sim.strings.translate(None, name));
+        }
+        event.text = Some(sim.strings.line(line));
+        event.shown = sim.reveal.as_ref().map(|r| r.shown);
+        event.prompt = line.prompt.is_some();
+        if let (Some(choices), None) = (line.choices.as_ref(), sim.reveal.as_ref()) {
+            let visible = choices
+                .choices
+                .iter()
+                .enumerate()
+                .filter(|(_, choice)| choice.guard.as_ref().is_none_or(|guard| sim.store.check_guard(guard)));
+            for ((choice_num, choice), index) in visible.zip(0..) {
+                if choice_num == choices.default_choice {
+                    event.default_choice = Some(index);
+                }
+                event.choices.push(Choice { index, text: sim.strings.choice(choice) });
+            }
+            event.time_left_ms = sim.time_left().map(|left| left.as_millis() as u64);
+        }
+        event
+    }
+}
+
+impl<'a> std::fmt::Display for JsonTup<'a, Sim> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let json = serde_json::to_string(&self.event()).map_err(|e| {
+            log::error!("Couldn't write the render as JSON due to {:?}.", e);
+            std::fmt::Error
+        })?;
+        writeln!(f, "{}", json)
+    }
+}
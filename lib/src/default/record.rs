@@ -0,0 +1,60 @@
+use super::Store;
+use crate::game;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{borrow::Borrow, collections::BTreeSet, io::Write, path::PathBuf};
+
+/// A set kept across playthroughs as a YAML list in the saves directory. New entries are appended
+/// to the file, rather than rewriting it.
+#[derive(Debug)]
+pub struct Record<T> {
+    entries: BTreeSet<T>,
+    path: PathBuf,
+}
+
+impl<T: Ord + Serialize + DeserializeOwned> Record<T> {
+    /// Starts from scratch if there's no record yet, or if it can't be read.
+    pub fn load(cfg: &game::Cfg<Store>, file: &str) -> Self {
+        let path = cfg.saves_dir().join(file);
+        let entries = if path.is_file() {
+            game::read_file(path.as_path()).unwrap_or_else(|e| {
+                log::warn!("Couldn't read {} due to {:?}, so starting it over.", path.display(), e);
+                // Appending to it would only keep it unreadable.
+                if let Err(e) = std::fs::remove_file(&path) {
+                    log::warn!("Couldn't remove {} due to {:?}.", path.display(), e);
+                }
+                BTreeSet::new()
+            })
+        } else {
+            BTreeSet::new()
+        };
+        Self { entries, path }
+    }
+
+    pub fn contains<Q: Ord + ?Sized>(&self, entry: &Q) -> bool
+    where
+        T: Borrow<Q>,
+    {
+        self.entries.contains(entry)
+    }
+
+    /// Adds the entry, saving right away if it's new. Returns whether it was.
+    pub fn insert(&mut self, entry: T) -> bool {
+        if self.entries.contains(&entry) {
+            return false;
+        }
+        if let Err(e) = self.append(&entry) {
+            log::warn!("Couldn't save to {} due to {:?}.", self.path.display(), e);
+        }
+        self.entries.insert(entry)
+    }
+
+    fn append(&self, entry: &T) -> std::io::Result<()> {
+        // JSON is also YAML, and keeps each entry on a line of its own.
+        let json = serde_json::to_string(entry)?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "- {}", json)
+    }
+}
@@ -0,0 +1,20 @@
+use super::{Record, Store};
+use crate::game;
+
+/// Every line the player has seen, across all sessions, by scene and line.
+#[derive(Debug)]
+pub struct Seen(Record<(String, usize)>);
+
+impl Seen {
+    pub fn load(cfg: &game::Cfg<Store>) -> Self {
+        Self(Record::load(cfg, "seen.yaml"))
+    }
+
+    pub fn contains(&self, scene: &str, line: usize) -> bool {
+        self.0.contains(&(scene.to_owned(), line))
+    }
+
+    pub fn insert(&mut self, scene: &str, line: usize) {
+        self.0.insert((scene.to_owned(), line));
+    }
+}
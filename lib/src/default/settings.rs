@@ -0,0 +1,280 @@
+use super::Store;
+use crate::game;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Player preferences, kept in the saves directory apart from story saves. Unset ones fall back to
+/// the game cfg, or to the built-in defaults.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// Characters revealed per second, overriding the game cfg. 0 shows lines all at once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_speed: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_advance: Option<bool>,
+    /// How long lines are shown for before moving on, not counting the time per character.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_advance_delay_ms: Option<u64>,
+    /// Whether speakers are shown in their colors. On by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<bool>,
+    /// Whether skipping carries on through lines the player hasn't seen yet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_unread: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_level: Option<Verbosity>,
+    /// Whether to start in TUI mode, as with `--tui`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tui: Option<bool>,
+}
+
+impl Settings {
+    const FILE: &'static str = "settings.yaml";
+
+    fn path(cfg: &game::Cfg<Store>) -> PathBuf {
+        cfg.saves_dir().join(Self::FILE)
+    }
+
+    /// Everything's unset if there are no settings yet, or if they can't be read.
+    pub fn load(cfg: &game::Cfg<Store>) -> Self {
+        let path = Self::path(cfg);
+        if !path.is_file() {
+            return Self::default();
+        }
+        game::read_file(path.as_path())
+            .map_err(|e| log::warn!("Couldn't read settings from {} due to {:?}.", path.display(), e))
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, cfg: &game::Cfg<Store>) {
+        let path = Self::path(cfg);
+        if let Err(e) = game::write_file(path.as_path(), self) {
+            log::warn!("Couldn't save settings to {} due to {:?}.", path.display(), e);
+        }
+    }
+
+    /// Takes every setting from `self`, and the ones `self` leaves unset from `other`.
+    pub fn or(self, other: Self) -> Self {
+        Self {
+            text_speed: self.text_speed.or(other.text_speed),
+            auto_advance: self.auto_advance.or(other.auto_advance),
+            auto_advance_delay_ms: self.auto_advance_delay_ms.or(other.auto_advance_delay_ms),
+            color: self.color.or(other.color),
+            skip_unread: self.skip_unread.or(other.skip_unread),
+            log_level: self.log_level.or(other.log_level),
+            tui: self.tui.or(other.tui),
+        }
+    }
+
+    /// The game cfg's pacing, with these settings on top.
+    pub fn pacing(&self, cfg: &game::Cfg<Store>) -> game::Pacing {
+        let mut pacing = cfg.pacing.clone();
+        if self.text_speed.is_some() {
+            pacing.text_speed = self.text_speed;
+        }
+        if self.auto_advance.unwrap_or(pacing.auto_advance.is_some()) {
+            pacing.auto_advance = Some(self.auto_advance_delay(cfg));
+        } else {
+            pacing.auto_advance = None;
+        }
+        pacing
+    }
+
+    /// How long auto-advance waits, even while it's off.
+    pub fn auto_advance_delay(&self, cfg: &game::Cfg<Store>) -> game::AutoAdvance {
+        let mut auto = cfg.pacing.auto_advance.clone().unwrap_or_default();
+        if let Some(ms) = self.auto_advance_delay_ms {
+            auto.base_ms = ms;
+        }
+        auto
+    }
+
+    pub fn color(&self) -> bool {
+        self.color.unwrap_or(true)
+    }
+
+    pub fn skip_unread(&self) -> bool {
+        self.skip_unread.unwrap_or(false)
+    }
+
+    pub fn tui(&self) -> bool {
+        self.tui.unwrap_or(false)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Verbosity {
+    pub const ALL: [Self; 6] = [
+        Verbosity::Off,
+        Verbosity::Error,
+        Verbosity::Warn,
+        Verbosity::Info,
+        Verbosity::Debug,
+        Verbosity::Trace,
+    ];
+
+    pub fn level_filter(self) -> log::LevelFilter {
+        match self {
+            Verbosity::Off => log::LevelFilter::Off,
+            Verbosity::Error => log::LevelFilter::Error,
+            Verbosity::Warn => log::LevelFilter::Warn,
+            Verbosity::Info => log::LevelFilter::Info,
+            Verbosity::Debug => log::LevelFilter::Debug,
+            Verbosity::Trace => log::LevelFilter::Trace,
+        }
+    }
+
+    /// The verbosity logs are at right now.
+    pub fn current() -> Self {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|v| v.level_filter() == log::max_level())
+            .unwrap_or(Verbosity::Info)
+    }
+}
+
+impl std::fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            Verbosity::Off => "off",
+            Verbosity::Error => "error",
+            Verbosity::Warn => "warn",
+            Verbosity::Info => "info",
+            Verbosity::Debug => "debug",
+            Verbosity::Trace => "trace",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|v| v.to_string().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("{:?} isn't a log level. Try one of off, error, warn, info, debug or trace.", s))
+    }
+}
+
+/// A setting on the settings screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Entry {
+    TextSpeed,
+    AutoAdvance,
+    AutoAdvanceDelay,
+    Color,
+    SkipUnread,
+    LogLevel,
+    Tui,
+}
+
+impl Entry {
+    pub(super) const ALL: [Self; 7] = [
+        Entry::TextSpeed,
+        Entry::AutoAdvance,
+        Entry::AutoAdvanceDelay,
+        Entry::Color,
+        Entry::SkipUnread,
+        Entry::LogLevel,
+        Entry::Tui,
+    ];
+    const TEXT_SPEEDS: [u32; 5] = [0, 15, 30, 60, 120];
+    const DELAYS_MS: [u64; 4] = [500, 1000, 2000, 4000];
+
+    /// Describes the setting as it is in `current`, the settings in effect.
+    pub(super) fn describe(self, cfg: &game::Cfg<Store>, current: &Settings) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let pacing = current.pacing(cfg);
+        match self {
+            Entry::TextSpeed => match pacing.text_speed {
+                Some(speed) if speed > 0 => format!("Text speed: {} characters per second", speed),
+                _ => "Text speed: instant".to_owned(),
+            },
+            Entry::AutoAdvance => format!("Auto-advance: {}", on_off(pacing.auto_advance.is_some())),
+            Entry::AutoAdvanceDelay => {
+                let auto = current.auto_advance_delay(cfg);
+                format!("Auto-advance delay: {}ms, plus {}ms per character", auto.base_ms, auto.per_char_ms)
+            }
+            Entry::Color => format!("Colors: {}", on_off(current.color())),
+            Entry::SkipUnread => format!("Skip unread lines: {}", on_off(current.skip_unread())),
+            Entry::LogLevel => format!("Log level: {}", current.log_level.unwrap_or_else(Verbosity::current)),
+            Entry::Tui => format!("Start in TUI mode: {} (from the next launch)", on_off(current.tui())),
+        }
+    }
+
+    /// Moves `settings` on to the next value after the one in `current`.
+    pub(super) fn cycle(self, cfg: &game::Cfg<Store>, current: &Settings, settings: &mut Settings) {
+        let pacing = current.pacing(cfg);
+        match self {
+            Entry::TextSpeed => {
+                let speed = pacing.text_speed.unwrap_or(0);
+                settings.text_speed = Some(Self::TEXT_SPEEDS.iter().copied().find(|s| *s > speed).unwrap_or(0));
+            }
+            Entry::AutoAdvance => settings.auto_advance = Some(pacing.auto_advance.is_none()),
+            Entry::AutoAdvanceDelay => {
+                let ms = current.auto_advance_delay(cfg).base_ms;
+                settings.auto_advance_delay_ms =
+                    Some(Self::DELAYS_MS.iter().copied().find(|d| *d > ms).unwrap_or(Self::DELAYS_MS[0]));
+            }
+            Entry::Color => settings.color = Some(!current.color()),
+            Entry::SkipUnread => settings.skip_unread = Some(!current.skip_unread()),
+            Entry::LogLevel => {
+                let level = current.log_level.unwrap_or_else(Verbosity::current);
+                let next = Verbosity::ALL.iter().position(|v| *v == level).map_or(0, |i| (i + 1) % Verbosity::ALL.len());
+                settings.log_level = Some(Verbosity::ALL[next]);
+            }
+            Entry::Tui => settings.tui = Some(!current.tui()),
+        }
+    }
+
+    /// Sets the setting in `settings` from what the player typed.
+    pub(super) fn set(self, settings: &mut Settings, value: &str) -> Result<(), String> {
+        fn parse_on_off(value: &str) -> Result<bool, String> {
+            match value.to_ascii_lowercase().as_str() {
+                "on" | "yes" | "true" => Ok(true),
+                "off" | "no" | "false" => Ok(false),
+                _ => Err(format!("{:?} should be on or off.", value)),
+            }
+        }
+        fn parse_number<T: std::str::FromStr>(value: &str) -> Result<T, String> {
+            value.parse().map_err(|_| format!("{:?} should be a whole number.", value))
+        }
+        match self {
+            Entry::TextSpeed => settings.text_speed = Some(parse_number(value)?),
+            Entry::AutoAdvance => settings.auto_advance = Some(parse_on_off(value)?),
+            Entry::AutoAdvanceDelay => settings.auto_advance_delay_ms = Some(parse_number(value)?),
+            Entry::Color => settings.color = Some(parse_on_off(value)?),
+            Entry::SkipUnread => settings.skip_unread = Some(parse_on_off(value)?),
+            Entry::LogLevel => settings.log_level = Some(value.parse()?),
+            Entry::Tui => settings.tui = Some(parse_on_off(value)?),
+        }
+        Ok(())
+    }
+
+    /// Unsets the setting in `settings`.
+    pub(super) fn clear(self, settings: &mut Settings) {
+        match self {
+            Entry::TextSpeed => settings.text_speed = None,
+            Entry::AutoAdvance => settings.auto_advance = None,
+            Entry::AutoAdvanceDelay => settings.auto_advance_delay_ms = None,
+            Entry::Color => settings.color = None,
+            Entry::SkipUnread => settings.skip_unread = None,
+            Entry::LogLevel => settings.log_level = None,
+            Entry::Tui => settings.tui = None,
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::Store;
+use crate::game;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::SystemTime};
+
+/// Where the player was, saved to a slot.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Save {
+    pub scene: String,
+    pub line: usize,
+    pub store: Store,
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+}
+
+impl Save {
+    pub fn new(scene: String, line: usize, store: Store) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self { scene, line, store, saved_at }
+    }
+
+    /// When it was saved, in local time.
+    pub fn saved_at_local(&self) -> String {
+        use chrono::TimeZone;
+        chrono::Local
+            .timestamp_opt(self.saved_at as i64, 0)
+            .single()
+            .map_or_else(|| "some time ago".to_owned(), |t| t.format("%Y-%m-%d %H:%M").to_string())
+    }
+}
+
+/// Save slots in the saves directory, numbered from 1.
+#[derive(Debug)]
+pub struct Slots {
+    dir: PathBuf,
+    count: usize,
+}
+
+impl Slots {
+    pub fn new(cfg: &game::Cfg<Store>) -> Self {
+        Self {
+            dir: cfg.saves_dir(),
+            count: cfg.save_slots,
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn path(&self, slot: usize) -> PathBuf {
+        self.dir.join(format!("slot_{}.yaml", slot))
+    }
+
+    fn check(&self, slot: usize) -> Result<(), String> {
+        if slot == 0 || slot > self.count {
+            Err(format!("There's no slot {}. Slots go from 1 to {}.", slot, self.count))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn save(&self, slot: usize, save: &Save) -> Result<(), String> {
+        self.check(slot)?;
+        game::write_file(self.path(slot).as_path(), save).map_err(|e| {
+            log::warn!("Couldn't save to slot {} due to {:?}.", slot, e);
+            format!("Couldn't save to slot {}.", slot)
+        })
+    }
+
+    pub fn load(&self, slot: usize) -> Result<Save, String> {
+        self.check(slot)?;
+        let path = self.path(slot);
+        if !path.is_file() {
+            return Err(format!("Slot {} is empty.", slot));
+        }
+        game::read_file(path.as_path()).map_err(|e| {
+            log::warn!("Couldn't load slot {} due to {:?}.", slot, e);
+            format!("Couldn't load slot {}.", slot)
+        })
+    }
+
+    /// What's in every slot, in order. Slots that are empty or can't be read are `None`.
+    pub fn list(&self) -> Vec<Option<Save>> {
+        (1..=self.count).map(|slot| self.load(slot).ok()).collect()
+    }
+
+    /// The slot saved to last, if any.
+    pub fn latest(&self) -> Option<usize> {
+        self.list()
+            .into_iter()
+            .zip(1..)
+            .filter_map(|(save, slot)| save.map(|save| (save.saved_at, slot)))
+            .max()
+            .map(|(_, slot)| slot)
+    }
+}
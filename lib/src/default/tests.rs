@@ -0,0 +1,741 @@
+use super::{Action, DisplayData, Record, Settings, Sim, Store};
+use crate::{exec::Sim as _, game, uial::display::RenderMode};
+use std::path::PathBuf;
+
+/// Writes a game to its own directory under the system's temp dir, and loads its cfg.
+fn project(name: &str, cfg: &str, scenes: &[(&str, &str)]) -> game::Cfg<Store> {
+    let root: PathBuf = std::env::temp_dir().join(format!("seren-test-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(root.join("scenes")).unwrap();
+    let scene_list: Vec<_> = scenes.iter().map(|(name, _)| format!("  - {}\n", name)).collect();
+    std::fs::write(
+        root.join("cfg.yaml"),
+        format!(
+            "scene_list:\n{}scenes: ./scenes/\nprimary_scene: {}\nsaves: ./saves/\n{}",
+            scene_list.concat(),
+            scenes[0].0,
+            cfg,
+        ),
+    )
+    .unwrap();
+    for (name, script) in scenes {
+        std::fs::write(root.join("scenes").join(format!("{}.seren", name)), script).unwrap();
+    }
+    game::Cfg::<Store>::load_from(&root).unwrap()
+}
+
+#[test]
+fn skipping_into_an_ending_stops_on_the_last_line() {
+    let cfg = project(
+        "skip-ending",
+        "endings:\n  good:\n    title: Good\n",
+        &[("a", "ALICE: Hello.\nBOB: Hi.\n@end good\n")],
+    );
+    let overrides = Settings {
+        skip_unread: Some(true),
+        ..Default::default()
+    };
+    let mut sim = Sim::init_overriding(&cfg, Default::default(), overrides).unwrap();
+    sim.resolve(&cfg, Action::Skip).unwrap();
+    assert_eq!(sim.ending.as_deref(), Some("good"));
+    assert_eq!(sim.curr_line, 1);
+    // Moving on from the ending still works.
+    sim.resolve(&cfg, Action::Progress).unwrap();
+}
+
+const MEETING: &str = "\
+endings:
+  good:
+    title: Good
+characters:
+  alice:
+    name: Alice
+    meters:
+      trust:
+        thresholds:
+          - above: 3
+            trigger:
+              scene_change:
+                name: secret
+";
+
+#[test]
+fn a_triggers_own_end_beats_a_meters_scene_change() {
+    let cfg = project(
+        "meter-end",
+        MEETING,
+        &[
+            ("a", "ALICE: Hello.\n~ alice.trust += 5\n-> @end good\n"),
+            ("secret", "ALICE: Between us...\n"),
+        ],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.ending.as_deref(), Some("good"));
+    assert_eq!(sim.scene.name(), "a");
+}
+
+#[test]
+fn a_meters_scene_change_beats_a_triggers_own() {
+    let cfg = project(
+        "meter-scene",
+        MEETING,
+        &[
+            ("a", "ALICE: Hello.\n~ alice.trust += 5\n-> elsewhere\n"),
+            ("secret", "ALICE: Between us...\n"),
+            ("elsewhere", "ALICE: Anyway.\n"),
+        ],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.ending, None);
+    assert_eq!(sim.scene.name(), "secret");
+    assert_eq!(sim.store.ints.get("alice.trust"), Some(&5));
+}
+
+#[test]
+fn achievements_unlock_once_their_condition_holds() {
+    let cfg = project(
+        "achievement",
+        "items:\n  coin: {}\nachievements:\n  rich:\n    title: Rich\n    condition: {Item: {item: coin}}\n",
+        &[("a", "Hello.\n~ give coin\nBye.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    assert!(!sim.achievements.is_unlocked("rich"));
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert!(sim.achievements.is_unlocked("rich"));
+}
+
+#[test]
+fn records_append_new_entries_and_read_them_back() {
+    let cfg = project("record", "", &[("a", "Hello.\n")]);
+    let mut record = Record::<(String, usize)>::load(&cfg, "seen.yaml");
+    assert!(record.insert(("a".to_owned(), 0)));
+    assert!(record.insert(("a".to_owned(), 2)));
+    assert!(!record.insert(("a".to_owned(), 0)));
+    let file = std::fs::read_to_string(cfg.saves_dir().join("seen.yaml")).unwrap();
+    assert_eq!(file, "- [\"a\",0]\n- [\"a\",2]\n");
+    let record = Record::<(String, usize)>::load(&cfg, "seen.yaml");
+    assert!(record.contains(&("a".to_owned(), 2)));
+    assert!(!record.contains(&("a".to_owned(), 1)));
+}
+
+#[test]
+fn unknown_speakers_are_reported_at_their_line() {
+    let cfg = project(
+        "unknown-speaker",
+        "characters:\n  alice:\n    name: Alice\n",
+        &[("a", "ALICE: Hello.\n\nThen it happened: boom.\nMALLORY: Psst.\n")],
+    );
+    let e = game::Scene::<Store>::load_from(&cfg.root.join(&cfg.scenes), "a", &cfg.characters).unwrap_err();
+    let d = match e {
+        game::LoadErr::ParseErr(d) => d,
+        e => panic!("Expected a diagnostic, got {:?}.", e),
+    };
+    assert_eq!(d.location, Some(game::Location { line: 4, column: 1 }));
+    assert_eq!(d.key_path.as_deref(), Some("lines[2].speaker"));
+    assert_eq!(d.snippet.as_deref(), Some("MALLORY: Psst."));
+}
+
+#[test]
+fn changing_an_unset_stat_starts_it_from_zero() {
+    let cfg = project("unset-stat", "", &[("a", "Hello.\n~ gold += 2\n~ luck -= 0.5\n~ toggle brave\nBye.\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.store.ints.get("gold"), Some(&2));
+    assert_eq!(sim.store.nums.get("luck"), Some(&-0.5));
+    assert_eq!(sim.store.bools.get("brave"), Some(&true));
+    sim.resolve(&cfg, Action::Debug("set gold += 1".to_owned())).unwrap();
+    sim.resolve(&cfg, Action::Debug("set silver += 1".to_owned())).unwrap();
+    assert_eq!(sim.store.ints.get("gold"), Some(&3));
+    assert_eq!(sim.store.ints.get("silver"), Some(&1));
+}
+
+#[test]
+fn skipping_a_reveal_starts_the_choice_timer() {
+    let cfg = project(
+        "skip-reveal",
+        "pacing:\n  text_speed: 10\n",
+        &[("a", "@timeout 5000\nPick one, quickly.\n** Left.\n* Right.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    assert!(sim.reveal.is_some());
+    assert_eq!(sim.choice_deadline, None);
+    sim.resolve(&cfg, Action::Skip).unwrap();
+    assert!(sim.reveal.is_none());
+    assert!(sim.choice_deadline.is_some());
+}
+
+fn error_after(sim: &mut Sim, cfg: &game::Cfg<Store>, action: Action) -> String {
+    let e = match sim.resolve(cfg, action) {
+        Ok(_) => panic!("Expected an error."),
+        Err(e) => e,
+    };
+    match sim.recover(cfg, e).unwrap() {
+        RenderMode::Render(DisplayData { error_text: Some(text), .. }) => text,
+        _ => panic!("Expected to recover with an error shown."),
+    }
+}
+
+#[test]
+fn picking_a_missing_choice_recovers_and_says_why() {
+    let cfg = project("missing-choice", "", &[("a", "Hello.\nPick one.\n** Left.\n* Right.\n* [if never] Hidden.\nAfter.\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    let text = error_after(&mut sim, &cfg, Action::Select(0));
+    assert!(text.contains("There are no choices here, so choice 1 can't be picked."), "{}", text);
+    assert_eq!(sim.curr_line, 0);
+
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.curr_line, 1);
+    for choice in [2, 5] {
+        let text = error_after(&mut sim, &cfg, Action::Select(choice));
+        assert!(text.contains(&format!("There's no choice {} here.", choice + 1)), "{}", text);
+        assert_eq!(sim.curr_line, 1);
+    }
+    // Still playable afterwards.
+    sim.resolve(&cfg, Action::Select(1)).unwrap();
+}
+
+#[test]
+fn a_missing_locale_is_blamed_on_the_locale() {
+    let cfg = project("missing-locale", "locale: fr\n", &[("a", "Hello.\n")]);
+    let e = match Sim::init(&cfg) {
+        Ok(_) => panic!("Expected the missing locale to fail."),
+        Err(crate::Error::Initialization(e)) => e,
+        Err(e) => panic!("Expected an initialization error, got {:?}.", e),
+    };
+    let text = e.to_string();
+    assert!(text.starts_with("Couldn't load the translations for fr from "), "{}", text);
+    assert!(text.ends_with(&format!("{}.", cfg.locales_dir().display())), "{}", text);
+}
+
+#[test]
+fn malformed_json_is_shown_and_the_session_carries_on() {
+    use crate::uial::{self, input::SystemAction};
+    let cfg = project("malformed-json", "", &[("a", "Hello.\nBye.\n")]);
+    let sim = Sim::init(&cfg).unwrap();
+    let (tx, input) = uial::input::channel();
+    let frames = uial::display::Frames::default();
+    let display = uial::display::shared::<_, _, _, super::TextRender>(frames.clone());
+    tx.send(uial::input::parse_json::<Action>("{\"action\": \"progres\"}").unwrap_or_else(SystemAction::Invalid))
+        .unwrap();
+    tx.send(SystemAction::Exit).unwrap();
+    let events = uial::event::Multiplexer::new().with_input(move || input);
+    super::run_event_loop(events, display, cfg, sim, Default::default()).unwrap();
+    let frame = frames.0.lock().unwrap();
+    assert_eq!(frame.count, 2);
+    assert!(frame.text.contains("Couldn't read \"{\\\"action\\\": \\\"progres\\\"}\" as an action"), "{}", frame.text);
+    assert!(frame.text.contains("Hello."), "{}", frame.text);
+}
+
+#[test]
+fn input_during_a_reveal_shows_the_rest_of_the_line() {
+    let cfg = project("reveal", "pacing:\n  text_speed: 10\n", &[("a", "Hello.\n@speed 0\nBye.\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    assert_eq!(sim.reveal.as_ref().map(|r| (r.shown, r.total)), Some((0, 6)));
+    match sim.resolve(&cfg, Action::Progress).unwrap() {
+        RenderMode::Render(DisplayData { revealed_from: Some(0), .. }) => (),
+        _ => panic!("Expected the rest of the line to be revealed."),
+    }
+    assert_eq!(sim.curr_line, 0);
+    assert!(sim.reveal.is_none());
+    // The line's own speed beats the cfg's, so it appears all at once.
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.curr_line, 1);
+    assert!(sim.reveal.is_none());
+}
+
+#[test]
+fn auto_advance_moves_on_but_waits_for_choices() {
+    let cfg = project(
+        "auto-advance",
+        "pacing:\n  auto_advance:\n    base_ms: 0\n    per_char_ms: 0\n",
+        &[("a", "Hello.\nPick one.\n** Left.\n* Right.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    assert!(sim.auto_advance_at.is_some());
+    sim.on_timer(&cfg).unwrap();
+    assert_eq!(sim.curr_line, 1);
+    assert_eq!(sim.auto_advance_at, None);
+    assert_eq!(sim.next_deadline(), None);
+}
+
+#[test]
+fn running_out_of_time_picks_the_timeout_choice() {
+    let cfg = project(
+        "timeout",
+        "",
+        &[
+            ("a", "@timeout 0 2\nRun!\n** Left. -> left\n* Right. -> right\n"),
+            ("left", "Went left.\n"),
+            ("right", "Went right.\n"),
+        ],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    assert!(sim.choice_deadline.is_some());
+    sim.on_timer(&cfg).unwrap();
+    assert_eq!(sim.scene.name(), "right");
+    assert_eq!(sim.choice_deadline, None);
+}
+
+#[test]
+fn the_countdown_ticks_until_time_runs_out() {
+    let cfg = project(
+        "countdown",
+        "",
+        &[("a", "@timeout 60000\nRun!\n** Left. -> left\n* Right.\n"), ("left", "Went left.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    let deadline = sim.choice_deadline.unwrap();
+    assert!(sim.next_deadline().unwrap() < deadline);
+    match sim.on_timer(&cfg).unwrap() {
+        RenderMode::Render(DisplayData { countdown: true, .. }) => (),
+        _ => panic!("Expected the countdown to be shown."),
+    }
+    assert_eq!(sim.curr_line, 0);
+    // Picking in time stops the clock, and the default is the `**` one.
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.scene.name(), "left");
+    assert_eq!(sim.choice_deadline, None);
+}
+
+#[test]
+fn prompts_store_answers_that_pass_their_rules() {
+    let cfg = project(
+        "prompt",
+        "",
+        &[("a", "GUARD: Password?\n? password: min 1, max 16\n[if password == \"swordfish\"] GUARD: Go on in.\nGUARD: Bye.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    let text = error_after(&mut sim, &cfg, Action::Progress);
+    assert_eq!(text, "The answer can't be empty.");
+    assert_eq!(sim.curr_line, 0);
+    sim.resolve(&cfg, Action::Entry("swordfish".to_owned())).unwrap();
+    assert_eq!(sim.store.strings.get("password").map(String::as_str), Some("swordfish"));
+    assert_eq!(sim.curr_line, 1);
+}
+
+#[test]
+fn commands_are_answers_at_a_prompt() {
+    let cfg = project("prompt-command", "", &[("a", "Your name?\n? name: one of help | Alice\n[if name == \"Alice\"] Hi.\nBye.\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Help).unwrap();
+    assert_eq!(sim.store.strings.get("name").map(String::as_str), Some("help"));
+    // The guard hides the line for any other answer.
+    assert_eq!(sim.curr_line, 2);
+}
+
+const ITEMS: &str = "\
+items:
+  coin:
+    name: Gold coin
+    stack: 5
+  potion:
+    name: Potion
+";
+
+#[test]
+fn items_stack_up_to_their_limit_and_guards_count_them() {
+    let cfg = project(
+        "items",
+        ITEMS,
+        &[("a", "Hello.\n~ give coin 4\n~ give coin 3\n~ take potion 2\n[if has coin >= 5] Rich.\nPoor.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.store.items.get("coin"), Some(&5));
+    assert_eq!(sim.store.items.get("potion"), None);
+    assert_eq!(sim.curr_line, 2);
+}
+
+#[test]
+fn consuming_more_than_is_held_recovers_and_says_why() {
+    use crate::uial::display::RenderGroup as _;
+    let cfg = project(
+        "consume",
+        ITEMS,
+        &[("a", "~ give coin\nBuy a potion?\n* Yes.\n    ~ consume coin 2\n    ~ give potion\n** No.\nAfter.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    let text = error_after(&mut sim, &cfg, Action::Select(0));
+    assert_eq!(text, "You need 2 Gold coin, but only have 1.");
+    assert_eq!(sim.store.items.get("coin"), Some(&1));
+    assert_eq!(sim.store.items.get("potion"), None);
+
+    let data = match sim.resolve(&cfg, Action::Inventory).unwrap() {
+        RenderMode::Render(data) => data,
+        RenderMode::Ignore => panic!("Expected the inventory to be shown."),
+    };
+    let shown = super::TextRender::create(&sim, &cfg, data).to_string();
+    assert!(shown.contains("Gold coin x1"), "{}", shown);
+    assert!(!shown.contains("Potion"), "{}", shown);
+}
+
+#[test]
+fn hooks_see_everything_that_happens_in_order() {
+    use super::hook::{Event, Hooks, Value};
+    use std::sync::{Arc, Mutex};
+    let cfg = project(
+        "hooks",
+        "endings:\n  good:\n    title: Good\n",
+        &[("a", "Hello.\nPick one.\n** Go. -> b\n    ~ gold += 2\n"), ("b", "ALICE: Hi.\n@end good\n")],
+    );
+    let events = Arc::new(Mutex::new(vec![]));
+    let seen = events.clone();
+    let hooks = Hooks::new().on(move |e| seen.lock().unwrap().push(e.clone()));
+    let mut sim = Sim::init_with(&cfg, hooks).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    sim.resolve(&cfg, Action::Select(0)).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    let events = events.lock().unwrap();
+    let line = |scene: &str, line, speaker: Option<&str>, text: &str| Event::LineShown {
+        scene: scene.to_owned(),
+        line,
+        speaker: speaker.map(str::to_owned),
+        text: text.to_owned(),
+    };
+    assert_eq!(
+        *events,
+        vec![
+            Event::SceneEntered { scene: "a".to_owned() },
+            line("a", 0, None, "Hello."),
+            line("a", 1, None, "Pick one."),
+            Event::ChoiceMade { scene: "a".to_owned(), line: 1, text: "Go.".to_owned(), by_player: true },
+            Event::StatChanged { name: "gold".to_owned(), old: None, new: Some(Value::Int(2)) },
+            Event::SceneExited { scene: "a".to_owned() },
+            Event::SceneEntered { scene: "b".to_owned() },
+            line("b", 0, Some("ALICE"), "Hi."),
+            Event::GameEnded { ending: Some("good".to_owned()) },
+        ],
+    );
+}
+
+#[test]
+fn endings_are_shown_and_remembered_across_playthroughs() {
+    use crate::uial::display::RenderGroup as _;
+    let cfg = project(
+        "endings",
+        "endings:\n  good:\n    title: Happily ever after\n  bad:\n    title: Alone\n",
+        &[("a", "Pick one.\n** Stay. -> @end good\n* Leave. -> @end bad\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Select(0)).unwrap();
+    assert_eq!(sim.ending.as_deref(), Some("good"));
+    assert_eq!(sim.next_deadline(), None);
+    let shown = super::TextRender::create(&sim, &cfg, DisplayData::default()).to_string();
+    assert!(shown.contains("THE END\nHappily ever after\nEndings reached: 1/2\n(Press enter to quit.)"), "{}", shown);
+    // Without a title screen, moving on from the ending quits.
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert!(sim.wants_exit());
+
+    let sim = Sim::init(&cfg).unwrap();
+    assert_eq!(sim.ending, None);
+    assert!(sim.endings.contains("good"));
+    assert!(!sim.endings.contains("bad"));
+}
+
+#[test]
+fn an_unknown_ending_is_blamed_on_the_scene() {
+    let cfg = project("unknown-ending", "endings:\n  good:\n    title: Good\n", &[("a", "Hello.\n@end great\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    let text = error_after(&mut sim, &cfg, Action::Progress);
+    assert_eq!(text, "Ending \"great\" isn't defined in the game cfg.");
+    assert_eq!(sim.ending, None);
+    assert_eq!(sim.curr_line, 0);
+}
+
+#[test]
+fn the_title_screen_starts_continues_and_quits() {
+    use crate::uial::display::RenderGroup as _;
+    let cfg = project(
+        "title",
+        "title_screen:\n  title: Seren\n  labels:\n    new_game: Begin\n",
+        &[("a", "Hello.\nHow are you?\nBye.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    let shown = super::TextRender::create(&sim, &cfg, DisplayData::default()).to_string();
+    // There's nothing to continue yet.
+    assert!(shown.contains("Seren\n1) Begin\n2) Load\n3) Settings\n4) Gallery\n5) Quit\n"), "{}", shown);
+
+    sim.resolve(&cfg, Action::Select(0)).unwrap();
+    assert!(sim.menus.is_empty());
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    sim.resolve(&cfg, Action::Save(1)).unwrap();
+    sim.resolve(&cfg, Action::Quit).unwrap();
+    assert_eq!(sim.menus, vec![super::menu::Screen::Title]);
+    assert!(!sim.wants_exit());
+
+    let shown = super::TextRender::create(&sim, &cfg, DisplayData::default()).to_string();
+    assert!(shown.contains("1) Begin\n2) Continue\n3) Load\n"), "{}", shown);
+    sim.resolve(&cfg, Action::Select(1)).unwrap();
+    assert!(sim.menus.is_empty());
+    assert_eq!(sim.curr_line, 1);
+
+    sim.resolve(&cfg, Action::Quit).unwrap();
+    sim.resolve(&cfg, Action::Select(5)).unwrap();
+    assert!(sim.wants_exit());
+}
+
+#[test]
+fn the_system_menu_pauses_the_story_and_resumes_it_where_it_was() {
+    use crate::uial::display::RenderGroup as _;
+    use super::menu::Screen;
+    let cfg = project("pause", "", &[("a", "Hello.\n@timeout 60000\nRun!\n** Left.\n* Right.\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    let deadline = sim.choice_deadline.unwrap();
+
+    sim.open_menu(&cfg).unwrap();
+    assert_eq!(sim.menus, vec![Screen::Pause]);
+    assert_eq!(sim.next_deadline(), None);
+    sim.resolve(&cfg, Action::Select(4)).unwrap();
+    let shown = super::TextRender::create(&sim, &cfg, DisplayData::default()).to_string();
+    assert!(shown.contains("Backlog:\n  Hello.\n  Run!\n"), "{}", shown);
+    // Esc backs out of one menu at a time.
+    sim.open_menu(&cfg).unwrap();
+    assert_eq!(sim.menus, vec![Screen::Pause]);
+
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    // Not a story action while paused, so it doesn't pick the default choice.
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert!(sim.menus.is_empty());
+    assert_eq!(sim.curr_line, 1);
+    assert!(sim.choice_deadline.unwrap() >= deadline + std::time::Duration::from_millis(10));
+}
+
+#[test]
+fn settings_changed_in_game_are_saved_and_beat_the_flags_they_replace() {
+    let cfg = project("settings", "pacing:\n  text_speed: 20\n", &[("a", "Hello.\n")]);
+    let flags = Settings {
+        text_speed: Some(30),
+        ..Default::default()
+    };
+    let mut sim = Sim::init_overriding(&cfg, Default::default(), flags.clone()).unwrap();
+    assert_eq!(sim.settings().pacing(&cfg).text_speed, Some(30));
+    sim.open_menu(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Select(3)).unwrap();
+    match sim.resolve(&cfg, Action::Entry("1 fast".to_owned())).unwrap() {
+        RenderMode::Render(DisplayData { error_text: Some(text), .. }) => assert_eq!(text, "\"fast\" should be a whole number."),
+        _ => panic!("Expected the value to be rejected."),
+    }
+    sim.resolve(&cfg, Action::Entry("1 45".to_owned())).unwrap();
+    sim.resolve(&cfg, Action::Select(3)).unwrap();
+    assert_eq!(sim.settings().pacing(&cfg).text_speed, Some(45));
+
+    let saved = Settings::load(&cfg);
+    assert_eq!(
+        saved,
+        Settings {
+            text_speed: Some(45),
+            color: Some(false),
+            ..Default::default()
+        },
+    );
+    // Flags still win on the next launch, and the saved settings fill in the rest.
+    let sim = Sim::init_overriding(&cfg, Default::default(), flags).unwrap();
+    assert_eq!(sim.settings().text_speed, Some(30));
+    assert!(!sim.settings().color());
+}
+
+#[test]
+fn lines_names_and_choices_are_translated_or_fall_back_to_the_source() {
+    use crate::uial::display::RenderGroup as _;
+    let cfg = project(
+        "locale",
+        "locale: fr\ncharacters:\n  alice:\n    name: Alice\n",
+        &[("a", "ALICE: Hello.\n@id pick\nPick one.\n** Stay.\n* Leave.\n")],
+    );
+    std::fs::create_dir_all(cfg.locales_dir()).unwrap();
+    std::fs::write(
+        cfg.locales_dir().join("fr.yaml"),
+        "Alice: Alicia\nHello.: Bonjour.\npick: Choisis.\nStay.: Rester.\n",
+    )
+    .unwrap();
+    let mut sim = Sim::init(&cfg).unwrap();
+    let shown = super::TextRender::create(&sim, &cfg, DisplayData::default()).to_string();
+    assert!(shown.contains("Alicia: Bonjour.\n"), "{}", shown);
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    let shown = super::TextRender::create(&sim, &cfg, DisplayData::default()).to_string();
+    assert!(shown.contains("Choisis.\n"), "{}", shown);
+    assert!(shown.contains("Rester."), "{}", shown);
+    assert!(shown.contains("Leave."), "{}", shown);
+
+    let template = game::locale::Table::extract(&cfg).unwrap();
+    let strings: Vec<_> = template.strings.iter().map(|(id, text)| (id.as_str(), text.as_str())).collect();
+    assert_eq!(
+        strings,
+        vec![("Alice", "Alice"), ("Hello.", "Hello."), ("Leave.", "Leave."), ("Stay.", "Stay."), ("pick", "Pick one.")],
+    );
+}
+
+#[test]
+fn typed_commands_are_read_as_actions() {
+    use crate::uial::input::{CustomAction, SystemAction};
+    let parse = |cmd: &str| Action::parse_input(Some(cmd.to_owned())).unwrap();
+    assert!(matches!(parse(""), SystemAction::Action(Action::Progress)));
+    assert!(matches!(parse("2"), SystemAction::Action(Action::Select(1))));
+    assert!(matches!(parse("save 3"), SystemAction::Action(Action::Save(3))));
+    assert!(matches!(parse("load 1"), SystemAction::Action(Action::Load(1))));
+    assert!(matches!(parse("back"), SystemAction::Action(Action::Back)));
+    assert!(matches!(parse("menu"), SystemAction::Menu));
+    // Not plain numbers, so they stay as typed, for prompts.
+    assert!(matches!(parse("007"), SystemAction::Action(Action::Entry(text)) if text == "007"));
+    assert!(matches!(parse("save me"), SystemAction::Action(Action::Entry(text)) if text == "save me"));
+    assert!(matches!(Action::parse_input(None).unwrap(), SystemAction::Exit));
+}
+
+#[test]
+fn commands_save_load_and_go_back_without_moving_the_story_on() {
+    let cfg = project("commands", "", &[("a", "Hello.\n~ gold += 1\nHow are you?\n~ gold += 1\nBye.\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    match sim.resolve(&cfg, Action::Entry("halp".to_owned())).unwrap() {
+        RenderMode::Render(DisplayData { error_text: Some(text), .. }) => {
+            assert_eq!(text, "Unknown command `halp`. Enter `help` to see what can be done.")
+        }
+        _ => panic!("Expected the command to be rejected."),
+    }
+    assert_eq!(sim.curr_line, 0);
+
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    sim.resolve(&cfg, Action::Save(2)).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    assert_eq!(sim.store.ints.get("gold"), Some(&2));
+    sim.resolve(&cfg, Action::Back).unwrap();
+    assert_eq!((sim.curr_line, sim.store.ints.get("gold")), (2, Some(&1)));
+    sim.resolve(&cfg, Action::Back).unwrap();
+    assert_eq!((sim.curr_line, sim.store.ints.get("gold")), (0, None));
+    match sim.resolve(&cfg, Action::Back).unwrap() {
+        RenderMode::Render(DisplayData { error_text: Some(text), .. }) => assert_eq!(text, "There's nothing to go back to."),
+        _ => panic!("Expected there to be nothing to go back to."),
+    }
+
+    sim.resolve(&cfg, Action::Load(2)).unwrap();
+    assert_eq!((sim.curr_line, sim.store.ints.get("gold")), (2, Some(&1)));
+    match sim.resolve(&cfg, Action::Load(3)).unwrap() {
+        RenderMode::Render(DisplayData { error_text: Some(_), .. }) => (),
+        _ => panic!("Expected an empty slot to fail to load."),
+    }
+    assert_eq!(sim.curr_line, 2);
+}
+
+/// What the console answered with, as `Err` if it was an error.
+fn console(sim: &mut Sim, cfg: &game::Cfg<Store>, input: &str) -> Result<String, String> {
+    match sim.resolve(cfg, Action::Debug(input.to_owned())).unwrap() {
+        RenderMode::Render(DisplayData { notice: Some(text), .. }) => Ok(text),
+        RenderMode::Render(DisplayData { error_text: Some(text), .. }) => Err(text),
+        _ => panic!("Expected the console to answer."),
+    }
+}
+
+#[test]
+fn the_console_changes_the_store_jumps_and_explains_guards() {
+    let cfg = project(
+        "console",
+        "",
+        &[("a", "Hello.\nPick one.\n** Leave.\n* [if gold >= 5 && !angry] Bribe.\n"), ("b", "Elsewhere.\nFar.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    assert_eq!(console(&mut sim, &cfg, "set gold += 3"), Ok("Applied `gold += 3`.".to_owned()));
+    assert_eq!(sim.store.ints.get("gold"), Some(&3));
+    assert_eq!(
+        console(&mut sim, &cfg, "eval gold >= 5 && !angry"),
+        Ok("`gold >= 5 && !angry` fails:\n  `gold >= 5` fails, as gold is 3".to_owned()),
+    );
+    assert_eq!(console(&mut sim, &cfg, "jump a:1"), Ok("Jumped to a:1.".to_owned()));
+    assert_eq!(
+        console(&mut sim, &cfg, "choices"),
+        Ok("Choices at a:1:\nVisible:\n  1) Leave. (default choice)\nHidden:\n  Bribe.\n    `gold >= 5` fails, as gold is 3".to_owned()),
+    );
+
+    assert_eq!(console(&mut sim, &cfg, "jump b:9"), Err("Scene b only has lines 0 to 1.".to_owned()));
+    assert_eq!(
+        console(&mut sim, &cfg, "set gold +"),
+        Err("Couldn't read the change: Stat change needs one of `=`, `+=` or `-=`.".to_owned()),
+    );
+    assert_eq!(
+        console(&mut sim, &cfg, "frobnicate"),
+        Err("Unknown console command `:frobnicate`. Enter `:help` to see what can be done.".to_owned()),
+    );
+    assert_eq!((sim.scene.name(), sim.curr_line), ("a", 1));
+}
+
+#[test]
+fn starting_partway_takes_the_given_store_over_a_new_games() {
+    let cfg = project(
+        "start",
+        "characters:\n  alice:\n    name: Alice\n    vars:\n      met: false\n      trust: 1\n",
+        &[("a", "Hello.\n"), ("b", "Elsewhere.\n~ gold += 1\n[if alice.met] ALICE: Again?\n")],
+    );
+    let mut store = Store::default();
+    store.set("alice.met", "true");
+    store.set("gold", "5");
+    store.set("code", "\"5\"");
+    let start = super::Start {
+        scene: Some("b".to_owned()),
+        line: 1,
+        store,
+    };
+    let sim = Sim::init_starting(&cfg, Default::default(), Settings::default(), start.clone()).unwrap();
+    // Starting on a trigger applies it, and moves on to the line after.
+    assert_eq!((sim.scene.name(), sim.curr_line), ("b", 2));
+    assert_eq!(sim.store.bools.get("alice.met"), Some(&true));
+    assert_eq!(sim.store.ints.get("alice.trust"), Some(&1));
+    assert_eq!(sim.store.ints.get("gold"), Some(&6));
+    assert_eq!(sim.store.strings.get("code").map(String::as_str), Some("5"));
+
+    let past_the_end = super::Start { line: 3, ..start };
+    match Sim::init_starting(&cfg, Default::default(), Settings::default(), past_the_end) {
+        Err(crate::Error::Resolution(e)) => assert_eq!(e.to_string(), "Scene b only has lines 0 to 2."),
+        _ => panic!("Expected starting past the end to fail."),
+    }
+}
+
+#[test]
+fn json_frames_number_choices_as_they_are_selected() {
+    use crate::uial::{display::RenderGroup as _, input::{self, SystemAction}};
+    let cfg = project(
+        "json",
+        "characters:\n  alice:\n    name: Alice\n",
+        &[("a", "ALICE: Pick one.\n* [if never] Hidden. -> b\n* Left. -> a\n** Right. -> b\n"), ("b", "Done.\n")],
+    );
+    let mut sim = Sim::init(&cfg).unwrap();
+    let frame = |sim: &Sim| -> serde_json::Value {
+        serde_json::from_str(&super::JsonRender::create(sim, &cfg, DisplayData::default()).to_string()).unwrap()
+    };
+    let shown = frame(&sim);
+    assert_eq!(shown["speaker_id"], "ALICE");
+    assert_eq!(shown["speaker"], "Alice");
+    assert_eq!(shown["text"], "Pick one.");
+    assert_eq!(shown["choices"], serde_json::json!([{ "index": 0, "text": "Left." }, { "index": 1, "text": "Right." }]));
+    assert_eq!(shown["default_choice"], 1);
+    assert_eq!(shown["screen"], serde_json::Value::Null);
+
+    let action = match input::parse_json::<Action>(r#"{"action": "select", "value": 1}"#).unwrap() {
+        SystemAction::Action(action) => action,
+        _ => panic!("Expected a story action."),
+    };
+    sim.resolve(&cfg, action).unwrap();
+    assert_eq!(frame(&sim)["scene"], "b");
+
+    assert!(matches!(input::parse_json::<Action>(r#"{"action": "menu"}"#), Ok(SystemAction::Menu)));
+    sim.open_menu(&cfg).unwrap();
+    let shown = frame(&sim);
+    assert_eq!(shown["screen"], "pause");
+    assert!(shown["screen_text"].as_str().unwrap().starts_with("Paused.\n1) Resume\n"), "{}", shown);
+    assert_eq!(shown["text"], serde_json::Value::Null);
+}
+
+#[test]
+fn missing_scenes_and_dead_ends_return_to_the_last_good_line() {
+    let cfg = project("recover", "", &[("a", "Hello.\nPick one.\n** Go on. -> nowhere\n* Stop.\n~ gold += 1\n")]);
+    let mut sim = Sim::init(&cfg).unwrap();
+    sim.resolve(&cfg, Action::Progress).unwrap();
+    let text = error_after(&mut sim, &cfg, Action::Progress);
+    assert!(text.starts_with("Couldn't load scene nowhere."), "{}", text);
+    assert_eq!((sim.scene.name(), sim.curr_line), ("a", 1));
+
+    // There's no line after the trigger, nor a scene to go on to.
+    let text = error_after(&mut sim, &cfg, Action::Select(1));
+    assert_eq!((sim.scene.name(), sim.curr_line), ("a", 1), "{}", text);
+    assert_eq!(sim.store.ints.get("gold"), None);
+}
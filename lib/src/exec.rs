@@ -1,10 +1,51 @@
 use crate::{game, uial};
 
 #[derive(Debug)]
-pub struct ResolutionErr(pub String);
+pub enum ResolutionErr {
+    SceneLoad { scene: String, err: game::LoadErr },
+    /// The player asked for something that isn't there, such as a hidden or out of range choice.
+    InvalidSelection(String),
+    /// There's nowhere for the story to go from the current line.
+    SoftLock(String),
+    /// The scene refers to something the game cfg doesn't define, such as an unknown item.
+    BadContent(String),
+    Other(String),
+}
+impl ResolutionErr {
+    /// Whether the session can continue after this error, by going back to the last valid line.
+    pub fn is_recoverable(&self) -> bool {
+        match self {
+            ResolutionErr::SceneLoad { .. }
+            | ResolutionErr::InvalidSelection(_)
+            | ResolutionErr::SoftLock(_)
+            | ResolutionErr::BadContent(_) => true,
+            ResolutionErr::Other(_) => false,
+        }
+    }
+}
 impl From<String> for ResolutionErr {
     fn from(e: String) -> Self {
-        ResolutionErr(e)
+        ResolutionErr::Other(e)
+    }
+}
+
+impl std::fmt::Display for ResolutionErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ResolutionErr::SceneLoad { scene, .. } => write!(f, "Couldn't load scene {}.", scene),
+            ResolutionErr::InvalidSelection(msg)
+            | ResolutionErr::SoftLock(msg)
+            | ResolutionErr::BadContent(msg)
+            | ResolutionErr::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+impl std::error::Error for ResolutionErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ResolutionErr::SceneLoad { err, .. } => Some(err),
+            _ => None,
+        }
     }
 }
 
@@ -21,4 +62,62 @@ pub trait Sim {
         cfg: &Self::Cfg,
         a: Self::ActionEnum,
     ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, ResolutionErr>;
+
+    /// Called with the error when `resolve` fails. Returning `Ok` keeps the session going with
+    /// whatever should be rendered instead. By default, every error ends the session.
+    fn recover(
+        &mut self,
+        _cfg: &Self::Cfg,
+        e: ResolutionErr,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, ResolutionErr> {
+        Err(e)
+    }
+
+    /// When the sim next wants `on_timer` called, if at all.
+    fn next_deadline(&self) -> Option<std::time::Instant> {
+        None
+    }
+
+    fn on_timer(
+        &mut self,
+        _cfg: &Self::Cfg,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, ResolutionErr> {
+        Ok(uial::display::RenderMode::Ignore)
+    }
+
+    /// Called when files being watched change on disk.
+    fn on_files_changed(
+        &mut self,
+        _cfg: &Self::Cfg,
+        _paths: &[std::path::PathBuf],
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, ResolutionErr> {
+        Ok(uial::display::RenderMode::Ignore)
+    }
+
+    /// Called when the player asks for the system menu. Not a story action, so by default it's
+    /// ignored.
+    fn open_menu(
+        &mut self,
+        _cfg: &Self::Cfg,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, ResolutionErr> {
+        Ok(uial::display::RenderMode::Ignore)
+    }
+
+    /// Called with what was wrong with input that couldn't be read as an action. By default, it
+    /// ends the session.
+    fn on_invalid_input(
+        &mut self,
+        _cfg: &Self::Cfg,
+        msg: String,
+    ) -> std::result::Result<uial::display::RenderMode<Self::DisplayData>, ResolutionErr> {
+        Err(ResolutionErr::Other(msg))
+    }
+
+    /// Checked after every event. Ends the session, as if the player quit, once true.
+    fn wants_exit(&self) -> bool {
+        false
+    }
+
+    /// Called once the player quits, before the session ends.
+    fn on_exit(&mut self, _cfg: &Self::Cfg) {}
 }
@@ -1,25 +1,48 @@
-use std::io;
-use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, io, path::{Path, PathBuf}};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 pub mod guard;
 pub mod trigger;
 pub mod choice;
 pub mod line;
+pub mod character;
+pub mod achievement;
+pub mod ending;
+pub mod menu;
+pub mod locale;
+pub mod item;
+pub mod prompt;
 
 mod state;
 pub use state::KeyedStore;
 
 mod cfg;
-pub use cfg::Cfg;
+pub use cfg::{AutoAdvance, Cfg, Pacing};
+
+pub mod format;
+pub use format::Format;
+
+pub mod script;
+
+mod diagnostic;
+pub use diagnostic::{Diagnostic, Location};
 
 #[derive(Debug)]
 pub enum InitErr {
     IOErr(io::Error),
-    ParseErr(serde_yaml::Error),
+    ParseErr(Box<Diagnostic>),
+    UnknownFormat(PathBuf),
+    /// The translations for `locale`, from the `dir` the game cfg keeps them in.
+    Locale { locale: String, dir: PathBuf, err: LoadErr },
+}
+impl From<Diagnostic> for InitErr {
+    fn from(e: Diagnostic) -> Self {
+        InitErr::ParseErr(Box::new(e))
+    }
 }
-impl From<serde_yaml::Error> for InitErr {
-    fn from(e: serde_yaml::Error) -> Self {
-        InitErr::ParseErr(e)
+impl From<format::Err> for InitErr {
+    fn from(e: format::Err) -> Self {
+        InitErr::ParseErr(Box::new(e.into()))
     }
 }
 impl From<io::Error> for InitErr {
@@ -32,6 +55,38 @@ impl From<LoadErr> for InitErr {
         match e {
             LoadErr::IOErr(e) => InitErr::IOErr(e),
             LoadErr::ParseErr(e) => InitErr::ParseErr(e),
+            LoadErr::UnknownFormat(p) => InitErr::UnknownFormat(p),
+        }
+    }
+}
+impl fmt::Display for InitErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitErr::IOErr(_) => write!(f, "Couldn't read the game cfg."),
+            InitErr::ParseErr(_) => write!(f, "The game cfg is invalid."),
+            InitErr::UnknownFormat(p) => write!(f, "{} isn't in a recognized format.", p.display()),
+            InitErr::Locale { locale, dir, .. } => {
+                write!(f, "Couldn't load the translations for {} from {}.", locale, dir.display())
+            }
+        }
+    }
+}
+impl std::error::Error for InitErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InitErr::IOErr(e) => Some(e),
+            InitErr::ParseErr(e) => Some(e.as_ref()),
+            InitErr::UnknownFormat(_) => None,
+            InitErr::Locale { err, .. } => Some(err),
+        }
+    }
+}
+impl InitErr {
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            InitErr::ParseErr(d) => Some(d),
+            InitErr::Locale { err, .. } => err.diagnostic(),
+            _ => None,
         }
     }
 }
@@ -39,11 +94,17 @@ impl From<LoadErr> for InitErr {
 #[derive(Debug)]
 pub enum LoadErr {
     IOErr(io::Error),
-    ParseErr(serde_yaml::Error),
+    ParseErr(Box<Diagnostic>),
+    UnknownFormat(PathBuf),
 }
-impl From<serde_yaml::Error> for LoadErr {
-    fn from(e: serde_yaml::Error) -> Self {
-        LoadErr::ParseErr(e)
+impl From<Diagnostic> for LoadErr {
+    fn from(e: Diagnostic) -> Self {
+        LoadErr::ParseErr(Box::new(e))
+    }
+}
+impl From<format::Err> for LoadErr {
+    fn from(e: format::Err) -> Self {
+        LoadErr::ParseErr(Box::new(e.into()))
     }
 }
 impl From<io::Error> for LoadErr {
@@ -56,20 +117,115 @@ impl From<InitErr> for LoadErr {
         match e {
             InitErr::IOErr(e) => LoadErr::IOErr(e),
             InitErr::ParseErr(e) => LoadErr::ParseErr(e),
+            InitErr::UnknownFormat(p) => LoadErr::UnknownFormat(p),
+            InitErr::Locale { err, .. } => err,
+        }
+    }
+}
+impl fmt::Display for LoadErr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadErr::IOErr(_) => write!(f, "Couldn't read the file."),
+            LoadErr::ParseErr(_) => write!(f, "The file is invalid."),
+            LoadErr::UnknownFormat(p) => write!(f, "{} isn't in a recognized format.", p.display()),
         }
     }
 }
+impl std::error::Error for LoadErr {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadErr::IOErr(e) => Some(e),
+            LoadErr::ParseErr(e) => Some(e.as_ref()),
+            LoadErr::UnknownFormat(_) => None,
+        }
+    }
+}
+impl LoadErr {
+    pub fn diagnostic(&self) -> Option<&Diagnostic> {
+        match self {
+            LoadErr::ParseErr(d) => Some(d),
+            _ => None,
+        }
+    }
 
+    fn in_scene(self, scene: &str) -> Self {
+        match self {
+            LoadErr::ParseErr(d) => LoadErr::ParseErr(Box::new(d.in_scene(scene))),
+            e => e,
+        }
+    }
+}
+
+/// Reads and deserializes a file, picking the format based on its extension.
+pub fn read_file<T: DeserializeOwned>(p: &Path) -> Result<T, LoadErr> {
+    let format = Format::from_path(p).ok_or_else(|| LoadErr::UnknownFormat(p.to_owned()))?;
+    log::debug!("Reading {} as {:?}.", p.display(), format);
+    let s = std::fs::read_to_string(p)?;
+    Ok(format.from_str(&s).map_err(|d| (*d).in_file(p.to_owned()))?)
+}
+
+/// Writes `t` to `p`, in the format matching its extension. Missing directories are created.
+pub fn write_file<T: Serialize>(p: &Path, t: &T) -> Result<(), LoadErr> {
+    let format = Format::from_path(p).ok_or_else(|| LoadErr::UnknownFormat(p.to_owned()))?;
+    if let Some(dir) = p.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(p, format.to_string(t)?)?;
+    Ok(())
+}
+
+/// Like `read_file`, but searches `dir` for `{stem}` with any of the supported extensions.
+pub fn find_and_read_file<T: DeserializeOwned>(dir: &Path, stem: &str) -> Result<T, LoadErr> {
+    let (p, _) = Format::find(dir, stem).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("No {} file found in {}.", stem, dir.display()),
+        )
+    })?;
+    read_file(p.as_path())
+}
+
+#[derive(Debug)]
 pub enum AbsenceError {
+    /// The line has no choices at all.
+    NoChoices,
     NotEnough,
     NotPresentAfterScan,
 }
+impl fmt::Display for AbsenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AbsenceError::NoChoices => write!(f, "There are no choices."),
+            AbsenceError::NotEnough => write!(f, "There aren't enough choices."),
+            AbsenceError::NotPresentAfterScan => write!(f, "The choice isn't available."),
+        }
+    }
+}
+impl std::error::Error for AbsenceError {}
 
+#[derive(Debug)]
 pub enum LineOrChoiceAbsenceError {
     LineDoesNotExist,
     SelectionIsTriggerNotLine,
     Choice(AbsenceError),
 }
+impl fmt::Display for LineOrChoiceAbsenceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineOrChoiceAbsenceError::LineDoesNotExist => write!(f, "The line doesn't exist."),
+            LineOrChoiceAbsenceError::SelectionIsTriggerNotLine => write!(f, "The line is a trigger, not a line."),
+            LineOrChoiceAbsenceError::Choice(_) => write!(f, "The choice can't be selected."),
+        }
+    }
+}
+impl std::error::Error for LineOrChoiceAbsenceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LineOrChoiceAbsenceError::Choice(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 impl From<AbsenceError> for LineOrChoiceAbsenceError {
     fn from(e: AbsenceError) -> Self {
         Self::Choice(e)
@@ -79,19 +235,84 @@ impl From<AbsenceError> for LineOrChoiceAbsenceError {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(untagged)]
 pub enum MainOrSceneChange {
-    Main(Option<usize>),
+    /// Written as the line, or as `main` for the start of the primary scene.
+    Main(#[serde(with = "main_line")] Option<usize>),
     SceneChange(trigger::SceneChange),
+    End(ending::End),
+}
+
+mod main_line {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Line(usize),
+        Keyword(String),
+    }
+
+    pub fn serialize<S: Serializer>(line: &Option<usize>, s: S) -> Result<S::Ok, S::Error> {
+        match line {
+            Some(line) => Repr::Line(*line),
+            None => Repr::Keyword("main".to_owned()),
+        }
+        .serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Option<usize>, D::Error> {
+        match Repr::deserialize(de)? {
+            Repr::Line(line) => Ok(Some(line)),
+            Repr::Keyword(word) if word == "main" => Ok(None),
+            Repr::Keyword(word) => Err(D::Error::custom(format!("Expected a line or `main`, not `{}`.", word))),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
 pub struct Scene<T: state::KeyedStore> {
     pub lines: Vec<line::GuardedLineOrTrigger<T>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub next_scene: Option<MainOrSceneChange>,
 }
 
 impl<T: state::KeyedStore> Scene<T> {
+    /// Reads a scene file, either as a script or as any of the serde formats. Once any
+    /// `characters` are given, every speaker must be one of them.
+    pub fn read_file(p: &Path, characters: &BTreeMap<String, character::Character<T>>) -> Result<Self, LoadErr> {
+        if p.extension().is_some_and(|ext| ext == script::EXTENSION) {
+            let s = std::fs::read_to_string(p)?;
+            let compiled = script::compile(&s)
+                .map_err(|e| Diagnostic::from_script(e, &s).in_file(p.to_owned()))?;
+            if let Some((idx, d)) = compiled.scene.unknown_speaker(characters) {
+                return Err(d.at(compiled.locations[idx], &s).in_file(p.to_owned()).into());
+            }
+            Ok(compiled.scene)
+        } else {
+            let scene: Self = read_file(p)?;
+            if let Some((_, d)) = scene.unknown_speaker(characters) {
+                return Err(d.in_file(p.to_owned()).into());
+            }
+            Ok(scene)
+        }
+    }
+
+    /// Loads the scene `name` from `dir`, preferring a script if one exists.
+    pub fn load_from(dir: &Path, name: &str, characters: &BTreeMap<String, character::Character<T>>) -> Result<Self, LoadErr> {
+        let script = dir.join(format!("{}.{}", name, script::EXTENSION));
+        let p = if script.is_file() {
+            script
+        } else {
+            Format::find(dir, name).map(|(p, _)| p).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No {} file found in {}.", name, dir.display()),
+                )
+            })?
+        };
+        Self::read_file(p.as_path(), characters).map_err(|e| e.in_scene(name))
+    }
+
     pub fn get_line_and_visible_choice(&self, store: &T, line: usize, choice: usize) -> Result<&choice::Choice<T>, LineOrChoiceAbsenceError> {
         let guarded_line = self.lines.get(line).ok_or(LineOrChoiceAbsenceError::LineDoesNotExist)?;
         let line = guarded_line.to_inner().line().ok_or(LineOrChoiceAbsenceError::SelectionIsTriggerNotLine)?;
@@ -106,10 +327,44 @@ impl<T: state::KeyedStore> Scene<T> {
         Ok(choice)
     }
 
-    pub fn is_line_choice(&self, line: usize) -> Result<bool, ()> {
-        self.lines.get(line)
-            .and_then(|l| l.to_inner().line())
-            .map(|l| l.choices.is_some())
-            .ok_or(())
+    pub fn get_line_and_timeout_choice(&self, store: &T, line: usize) -> Result<&choice::Choice<T>, LineOrChoiceAbsenceError> {
+        let guarded_line = self.lines.get(line).ok_or(LineOrChoiceAbsenceError::LineDoesNotExist)?;
+        let line = guarded_line.to_inner().line().ok_or(LineOrChoiceAbsenceError::SelectionIsTriggerNotLine)?;
+        let choice = line.try_get_timeout_choice(store)?;
+        Ok(choice)
+    }
+
+    pub fn is_line_prompt(&self, line: usize) -> Result<bool, LineOrChoiceAbsenceError> {
+        let guarded_line = self.lines.get(line).ok_or(LineOrChoiceAbsenceError::LineDoesNotExist)?;
+        let line = guarded_line.to_inner().line().ok_or(LineOrChoiceAbsenceError::SelectionIsTriggerNotLine)?;
+        Ok(line.prompt.is_some())
+    }
+
+    /// The first line spoken by someone who isn't a known character, along with why. Anything
+    /// goes if no characters are defined.
+    fn unknown_speaker(&self, characters: &BTreeMap<String, character::Character<T>>) -> Option<(usize, Diagnostic)> {
+        if characters.is_empty() {
+            return None;
+        }
+        self.lines.iter().enumerate().find_map(|(idx, line)| {
+            let speaker = line.to_inner().line()?.speaker.as_ref()?;
+            if character::find(characters, speaker).is_some() {
+                return None;
+            }
+            let known: Vec<_> = characters.keys().map(String::as_str).collect();
+            let d = Diagnostic::new(format!(
+                "`{}` isn't a known character. Characters are {}.",
+                speaker,
+                known.join(", "),
+            ))
+            .at_key(format!("lines[{}].speaker", idx));
+            Some((idx, d))
+        })
+    }
+
+    pub fn is_line_choice(&self, line: usize) -> Result<bool, LineOrChoiceAbsenceError> {
+        let guarded_line = self.lines.get(line).ok_or(LineOrChoiceAbsenceError::LineDoesNotExist)?;
+        let line = guarded_line.to_inner().line().ok_or(LineOrChoiceAbsenceError::SelectionIsTriggerNotLine)?;
+        Ok(line.choices.is_some())
     }
 }
@@ -0,0 +1,14 @@
+use crate::game::{self, KeyedStore};
+use serde::{Deserialize, Serialize};
+
+/// An achievement from the game cfg. Unlocked by a trigger with `unlocks`, or once `condition`
+/// holds after any trigger.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct Achievement<T: KeyedStore> {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub condition: Option<game::guard::Guard<T>>,
+}
@@ -1,9 +1,10 @@
-use crate::game;
+use crate::game::{self, KeyedStore};
 use serde::{Deserialize, Serialize};
-use std::{fs::File, io::BufReader, path::Path};
+use std::{collections::BTreeMap, path::Path};
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Cfg {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct Cfg<T: KeyedStore> {
     /// List of scenes that should exist in the scenes directory.
     pub scene_list: Vec<String>,
     /// Game data files location.
@@ -12,17 +13,102 @@ pub struct Cfg {
     pub saves: std::path::PathBuf,
     /// Initial scene.
     pub primary_scene: String,
+    /// Where translations are kept, one file per locale.
+    #[serde(default = "default_locales")]
+    pub locales: std::path::PathBuf,
+    /// Locale to show text in. Defaults to the language the scenes are written in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Shown before the story starts, if given. Otherwise, the story starts right away.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title_screen: Option<game::menu::TitleScreen>,
+    /// How many slots there are to save the story in.
+    #[serde(default = "default_save_slots")]
+    pub save_slots: usize,
+    #[serde(default)]
+    pub pacing: Pacing,
+    /// Items the player can hold, by id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub items: BTreeMap<String, game::item::Item>,
+    /// Characters lines can be spoken by, by id. If any are given, every speaker must be one of them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub characters: BTreeMap<String, game::character::Character<T>>,
+    /// Achievements the player can unlock, by id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub achievements: BTreeMap<String, game::achievement::Achievement<T>>,
+    /// Endings the story can reach, by id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub endings: BTreeMap<String, game::ending::Ending>,
     /// Location of the config file. Updated when read, not intended to be saved.
     #[serde(skip)]
     pub root: std::path::PathBuf,
 }
 
-impl Cfg {
-    pub fn load_from(p: &Path) -> Result<Cfg, game::InitErr> {
-        let f = File::open(p.join("cfg.yaml"))?;
-        let buf = BufReader::new(f);
-        let mut cfg: Self = serde_yaml::from_reader(buf)?;
+/// How fast text appears, and whether the game moves on by itself.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Pacing {
+    /// Characters revealed per second. Lines appear all at once if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_speed: Option<u32>,
+    /// Moves on from lines without choices once they've been shown for a while.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_advance: Option<AutoAdvance>,
+}
+
+/// Lines are shown for `base_ms`, plus `per_char_ms` for every character, before moving on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AutoAdvance {
+    #[serde(default = "AutoAdvance::default_base_ms")]
+    pub base_ms: u64,
+    #[serde(default = "AutoAdvance::default_per_char_ms")]
+    pub per_char_ms: u64,
+}
+
+impl AutoAdvance {
+    fn default_base_ms() -> u64 {
+        1000
+    }
+
+    fn default_per_char_ms() -> u64 {
+        50
+    }
+
+    pub fn delay_for(&self, chars: usize) -> std::time::Duration {
+        std::time::Duration::from_millis(self.base_ms + self.per_char_ms * chars as u64)
+    }
+}
+
+impl Default for AutoAdvance {
+    fn default() -> Self {
+        Self {
+            base_ms: Self::default_base_ms(),
+            per_char_ms: Self::default_per_char_ms(),
+        }
+    }
+}
+
+fn default_save_slots() -> usize {
+    5
+}
+
+fn default_locales() -> std::path::PathBuf {
+    "locales".into()
+}
+
+impl<T: KeyedStore> Cfg<T> {
+    /// Loads `cfg.{yaml,json,toml,ron}` from the directory `p`.
+    pub fn load_from(p: &Path) -> Result<Self, game::InitErr> {
+        let mut cfg: Self = game::find_and_read_file(p, "cfg")?;
         cfg.root = p.to_owned();
         Ok(cfg)
     }
+
+    pub fn locales_dir(&self) -> std::path::PathBuf {
+        self.root.join(self.locales.as_path())
+    }
+
+    /// Where saves and other per-player records go.
+    pub fn saves_dir(&self) -> std::path::PathBuf {
+        self.root.join(self.saves.as_path())
+    }
 }
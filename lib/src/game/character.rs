@@ -0,0 +1,91 @@
+use crate::game::{self, KeyedStore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A character from the game cfg, who lines refer to by id.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct Character<T: KeyedStore> {
+    /// Name shown when they speak.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    /// Shown after the name, such as `Captain of the Guard`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Starting values of their stats, stored as `id.stat`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, Var>,
+    /// How they feel about the player, such as trust. Also stored as `id.meter`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meters: BTreeMap<String, Meter<T>>,
+}
+
+/// An integer stat that stays within bounds, and sets off triggers when it crosses thresholds.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct Meter<T: KeyedStore> {
+    #[serde(default)]
+    pub start: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<i64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub thresholds: Vec<Threshold<T>>,
+}
+
+impl<T: KeyedStore> Meter<T> {
+    pub fn clamp(&self, value: i64) -> i64 {
+        let value = self.min.map_or(value, |min| value.max(min));
+        self.max.map_or(value, |max| value.min(max))
+    }
+}
+
+/// Fires `trigger` when the meter goes from at most `above` to more than it, or from at least
+/// `below` to less than it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
+pub struct Threshold<T: KeyedStore> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub above: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub below: Option<i64>,
+    pub trigger: game::trigger::Trigger<T>,
+}
+
+impl<T: KeyedStore> Threshold<T> {
+    pub fn is_crossed(&self, old: i64, new: i64) -> bool {
+        self.above.is_some_and(|above| old <= above && new > above)
+            || self.below.is_some_and(|below| old >= below && new < below)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum Var {
+    Bool(bool),
+    Int(i64),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+/// Finds the character a line's speaker refers to. Ids are matched ignoring case, so that
+/// screenplay-style `ALICE:` finds `alice`.
+pub fn find<'a, T: KeyedStore>(characters: &'a BTreeMap<String, Character<T>>, speaker: &str) -> Option<(&'a str, &'a Character<T>)> {
+    characters
+        .get_key_value(speaker)
+        .or_else(|| characters.iter().find(|(id, _)| id.eq_ignore_ascii_case(speaker)))
+        .map(|(id, c)| (id.as_str(), c))
+}
@@ -6,9 +6,13 @@ use serde::{Deserialize, Serialize};
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
 pub struct Choice<T: KeyedStore> {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub guard: Option<guard::Guard<T>>,
+    /// In the source language. Used when there's no translation.
     pub text: String,
-    #[serde(default)]
+    /// Looks up translations by this instead of by `text`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub trigger: Option<trigger::Trigger<T>>,
 }
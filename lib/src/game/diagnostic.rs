@@ -0,0 +1,130 @@
+use crate::game::{format, script};
+use std::{fmt, path::PathBuf};
+
+/// 1-indexed position in a source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// `by` columns further along the same line.
+    pub(crate) fn offset(self, by: usize) -> Self {
+        Self {
+            line: self.line,
+            column: self.column + by,
+        }
+    }
+}
+
+/// Everything we know about why a file failed to load, so authors can find the mistake quickly.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub scene: Option<String>,
+    pub location: Option<Location>,
+    /// Path to the offending value, such as `lines[3].choices[1].trigger`.
+    pub key_path: Option<String>,
+    /// The source line the location points into.
+    pub snippet: Option<String>,
+    pub cause: Option<format::Err>,
+}
+
+impl Diagnostic {
+    pub fn new(message: String) -> Self {
+        Self {
+            message,
+            file: None,
+            scene: None,
+            location: None,
+            key_path: None,
+            snippet: None,
+            cause: None,
+        }
+    }
+
+    /// Fills in the location, along with the source line it points to.
+    pub fn at(mut self, location: Location, src: &str) -> Self {
+        self.snippet = src.lines().nth(location.line.saturating_sub(1)).map(str::to_owned);
+        self.location = Some(location);
+        self
+    }
+
+    /// Fills in the location from a byte offset into the source.
+    pub fn at_offset(self, offset: usize, src: &str) -> Self {
+        let before = &src[..offset.min(src.len())];
+        let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+        let location = Location {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        };
+        self.at(location, src)
+    }
+
+    pub fn at_key(mut self, key_path: String) -> Self {
+        self.key_path = Some(key_path);
+        self
+    }
+
+    pub fn in_file(mut self, file: PathBuf) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    pub fn in_scene(mut self, scene: &str) -> Self {
+        self.scene = Some(scene.to_owned());
+        self
+    }
+
+    pub fn from_script(e: script::Err, src: &str) -> Self {
+        let script::Err { span, msg } = e;
+        Self::new(msg).at(span, src)
+    }
+}
+
+impl std::error::Error for Diagnostic {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause.as_ref().map(|e| e as _)
+    }
+}
+
+impl From<format::Err> for Diagnostic {
+    fn from(e: format::Err) -> Self {
+        let mut d = Self::new(e.message());
+        d.cause = Some(e);
+        d
+    }
+}
+
+/// Renders in the style of `rustc`, with the offending line and a marker under the column.
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        let gutter = self.location.map_or(0, |l| l.line.to_string().len());
+        let pad = " ".repeat(gutter);
+        if let Some(file) = self.file.as_ref() {
+            write!(f, "\n{}--> {}", pad, file.display())?;
+            if let Some(Location { line, column }) = self.location {
+                write!(f, ":{}:{}", line, column)?;
+            }
+        }
+        if let (Some(Location { line, column }), Some(snippet)) = (self.location, self.snippet.as_ref()) {
+            let marker_pad: String = snippet
+                .chars()
+                .take(column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            write!(f, "\n{} |", pad)?;
+            write!(f, "\n{} | {}", line, snippet)?;
+            write!(f, "\n{} | {}^", pad, marker_pad)?;
+        }
+        match (self.scene.as_ref(), self.key_path.as_ref()) {
+            (Some(scene), Some(path)) => write!(f, "\n{} = in scene `{}`, at `{}`", pad, scene, path),
+            (Some(scene), None) => write!(f, "\n{} = in scene `{}`", pad, scene),
+            (None, Some(path)) => write!(f, "\n{} = at `{}`", pad, path),
+            (None, None) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// An ending from the game cfg.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Ending {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Ends the story with the ending `end`, instead of going on to another scene.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct End {
+    pub end: String,
+}
@@ -0,0 +1,351 @@
+use crate::game::diagnostic::{Diagnostic, Location};
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Serialization formats that game data can be stored in. Detected by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Yaml,
+    Json,
+    Toml,
+    Ron,
+}
+
+#[derive(Debug)]
+pub enum Err {
+    Yaml(serde_yaml::Error),
+    Json(serde_json::Error),
+    TomlDe(toml::de::Error),
+    TomlSer(toml::ser::Error),
+    Ron(ron::Error),
+}
+impl From<serde_yaml::Error> for Err {
+    fn from(e: serde_yaml::Error) -> Self {
+        Err::Yaml(e)
+    }
+}
+impl From<serde_json::Error> for Err {
+    fn from(e: serde_json::Error) -> Self {
+        Err::Json(e)
+    }
+}
+impl From<toml::de::Error> for Err {
+    fn from(e: toml::de::Error) -> Self {
+        Err::TomlDe(e)
+    }
+}
+impl From<toml::ser::Error> for Err {
+    fn from(e: toml::ser::Error) -> Self {
+        Err::TomlSer(e)
+    }
+}
+impl From<ron::Error> for Err {
+    fn from(e: ron::Error) -> Self {
+        Err::Ron(e)
+    }
+}
+
+impl std::fmt::Display for Err {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Err::Yaml(e) => write!(f, "{}", e),
+            Err::Json(e) => write!(f, "{}", e),
+            Err::TomlDe(e) => write!(f, "{}", e),
+            Err::TomlSer(e) => write!(f, "{}", e),
+            Err::Ron(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl std::error::Error for Err {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Err::Yaml(e) => Some(e),
+            Err::Json(e) => Some(e),
+            Err::TomlDe(e) => Some(e),
+            Err::TomlSer(e) => Some(e),
+            Err::Ron(e) => Some(e),
+        }
+    }
+}
+
+impl Err {
+    /// The error message, without any position information the underlying error adds.
+    pub fn message(&self) -> String {
+        fn strip_position(s: String) -> String {
+            match s.rfind(" at line ") {
+                Some(idx) => s[..idx].to_owned(),
+                None => s,
+            }
+        }
+        // YAML errors are prefixed with the key path, which is reported separately.
+        fn strip_path(s: String) -> String {
+            match s.find(": ") {
+                Some(idx) if s[..idx].chars().all(|c| c.is_alphanumeric() || "_.[]".contains(c)) => {
+                    s[idx + 2..].to_owned()
+                }
+                _ => s,
+            }
+        }
+        match self {
+            Err::Yaml(e) => strip_path(strip_position(e.to_string())),
+            Err::Json(e) => strip_position(e.to_string()),
+            Err::TomlDe(e) => e.message().to_owned(),
+            Err::TomlSer(e) => e.to_string(),
+            Err::Ron(e) => e.code.to_string(),
+        }
+    }
+
+    /// Converts into a diagnostic, pulling out where in `src` the error happened if possible.
+    fn into_diagnostic(self, src: &str) -> Diagnostic {
+        let location = match &self {
+            Err::Yaml(e) => e.location().map(|l| Location {
+                line: l.line(),
+                column: l.column(),
+            }),
+            Err::Json(e) if e.line() > 0 => Some(Location {
+                line: e.line(),
+                column: e.column(),
+            }),
+            Err::Ron(e) if e.position.line > 0 => Some(Location {
+                line: e.position.line,
+                column: e.position.col,
+            }),
+            _ => None,
+        };
+        let offset = match &self {
+            Err::TomlDe(e) => e.span().map(|span| span.start),
+            _ => None,
+        };
+        let d = Diagnostic::from(self);
+        match (location, offset) {
+            (Some(location), _) => d.at(location, src),
+            (None, Some(offset)) => d.at_offset(offset, src),
+            (None, None) => d,
+        }
+    }
+}
+
+impl Format {
+    /// Lookup order when searching for a file by stem. YAML stays first, since that's what most
+    /// projects use.
+    pub const ALL: [Format; 4] = [Format::Yaml, Format::Json, Format::Toml, Format::Ron];
+
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Format::Yaml => &["yaml", "yml"],
+            Format::Json => &["json"],
+            Format::Toml => &["toml"],
+            Format::Ron => &["ron"],
+        }
+    }
+
+    pub fn from_extension(ext: &str) -> Option<Format> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|f| f.extensions().iter().any(|e| e.eq_ignore_ascii_case(ext)))
+    }
+
+    pub fn from_path(p: &Path) -> Option<Format> {
+        p.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension)
+    }
+
+    /// Finds `{stem}.{ext}` in `dir` for the first extension (in `Format::ALL` order) that exists.
+    pub fn find(dir: &Path, stem: &str) -> Option<(PathBuf, Format)> {
+        Self::ALL
+            .iter()
+            .flat_map(|f| f.extensions().iter().map(move |ext| (*f, ext)))
+            .map(|(f, ext)| (dir.join(format!("{}.{}", stem, ext)), f))
+            .find(|(p, _)| p.is_file())
+    }
+
+    /// Deserializes `s`, reporting errors at the path to the offending value, such as
+    /// `lines[3].guarded.choices[1].trigger`.
+    pub fn from_str<T: DeserializeOwned>(self, s: &str) -> Result<T, Box<Diagnostic>> {
+        fn convert<E: Into<Err>>(e: serde_path_to_error::Error<E>, s: &str) -> Diagnostic {
+            let path = e.path().to_string();
+            let mut d = e.into_inner().into().into_diagnostic(s);
+            // The root path is rendered as `.`, which isn't helpful.
+            if path != "." {
+                d.key_path = Some(path);
+            }
+            d
+        }
+        let t = match self {
+            Format::Yaml => serde_path_to_error::deserialize(serde_yaml::Deserializer::from_str(s))
+                .map_err(|e| convert(e, s)),
+            Format::Json => {
+                let mut de = serde_json::Deserializer::from_str(s);
+                let t = serde_path_to_error::deserialize(&mut de).map_err(|e| convert(e, s))?;
+                de.end().map_err(|e| Err::from(e).into_diagnostic(s))?;
+                Ok(t)
+            }
+            Format::Toml => serde_path_to_error::deserialize(toml::Deserializer::new(s))
+                .map_err(|e| convert(e, s)),
+            Format::Ron => {
+                let mut de = ron::Deserializer::from_str(s).map_err(|e| Err::from(e).into_diagnostic(s))?;
+                let t = serde_path_to_error::deserialize(&mut de).map_err(|e| convert(e, s))?;
+                de.end().map_err(|e| Err::from(e).into_diagnostic(s))?;
+                Ok(t)
+            }
+        };
+        t.map_err(Box::new)
+    }
+
+    pub fn to_string<T: Serialize>(self, t: &T) -> Result<String, Err> {
+        let s = match self {
+            Format::Yaml => serde_yaml::to_string(t)?,
+            Format::Json => serde_json::to_string_pretty(t)?,
+            Format::Toml => toml::to_string_pretty(t)?,
+            Format::Ron => ron::ser::to_string_pretty(t, ron::ser::PrettyConfig::new())?,
+        };
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{default::Store, game::Scene};
+
+    const CHOICE: &str = "lines:\n  - guarded:\n      text: Hi.\n      default_choice: 0\n      choices:\n        - text: Go.\n";
+
+    #[test]
+    fn lines_load_with_their_choices() {
+        let s = format!("{}          trigger:\n            scene_change: {{name: b}}\n", CHOICE);
+        let scene = Format::Yaml.from_str::<Scene<Store>>(&s).unwrap();
+        let line = scene.lines[0].to_inner().line().unwrap();
+        assert!(line.choices.as_ref().unwrap().choices[0].trigger.is_some());
+    }
+
+    #[test]
+    fn errors_inside_a_line_point_at_their_field() {
+        let s = format!("{}          trigger:\n            scene_change: 5\n", CHOICE);
+        let d = Format::Yaml.from_str::<Scene<Store>>(&s).unwrap_err();
+        assert_eq!(d.key_path.as_deref(), Some("lines[0].guarded.choices[0].trigger.scene_change"));
+        assert_eq!(d.location, Some(Location { line: 8, column: 27 }));
+        assert!(d.message.contains("expected struct SceneChange"), "{}", d.message);
+
+        let d = Format::Yaml.from_str::<Scene<Store>>(&CHOICE.replace("      default_choice: 0\n", "")).unwrap_err();
+        assert_eq!(d.key_path.as_deref(), Some("lines[0].guarded"));
+        assert!(d.message.contains("default_choice"), "{}", d.message);
+
+        let d = Format::Yaml.from_str::<Scene<Store>>("lines:\n  - guarded:\n      txt: Hi.\n").unwrap_err();
+        assert_eq!(d.key_path.as_deref(), Some("lines[0].guarded.txt"));
+        assert!(d.message.contains("unknown field `txt`"), "{}", d.message);
+    }
+
+    #[test]
+    fn lines_and_triggers_keep_their_fields_apart() {
+        let d = Format::Yaml.from_str::<Scene<Store>>("lines:\n  - guarded:\n      text: Hi.\n      end: good\n").unwrap_err();
+        assert!(d.message.contains("`end` is only for triggers"), "{}", d.message);
+        let d = Format::Json.from_str::<Scene<Store>>(r#"{"lines": [{"guarded": {"speaker": "alice"}}]}"#).unwrap_err();
+        assert!(d.message.contains("`speaker` is only for lines"), "{}", d.message);
+        let scene = Format::Json.from_str::<Scene<Store>>(r#"{"lines": [{"guarded": {"end": "good"}}]}"#).unwrap();
+        assert_eq!(scene.lines[0].to_inner().trigger().unwrap().end.as_deref(), Some("good"));
+    }
+
+    const STORY: &str = "\
+@main 2
+ALICE: Hello there.
+    Indented.
+@pause 1500
+@speed 5
+@timeout 5000 2
+@id hi
+BOB: Run!
+* [if bossy >= 2 && !humble || luck < 0.5] Left. -> b:4
+    ~ guilt += 1
+    ~ luck -= 0.25
+** Right.
+    ~ gold = 5
+    ~ ratio = 0.5
+    ~ brave = true
+    ~ toggle brave
+    -> @end good
+GUARD: Password?
+? password: min 1, max 16, alphanumeric, numeric, one of a | b
+[if password == \"swordfish\"] GUARD: Go on in.
+[if has coin >= 3] ~ consume coin 3
+~ give potion
+~ take key 2
+~ unlock generous
+-> c
+";
+
+    fn round_trip<T: Serialize + DeserializeOwned>(t: &T) {
+        let expected = serde_json::to_value(t).unwrap();
+        for format in Format::ALL.iter() {
+            let written = format.to_string(t).unwrap();
+            let read: T = format
+                .from_str(&written)
+                .unwrap_or_else(|d| panic!("Couldn't read back {:?} due to {}, from:\n{}", format, d, written));
+            assert_eq!(serde_json::to_value(&read).unwrap(), expected, "{:?} wrote:\n{}", format, written);
+        }
+    }
+
+    #[test]
+    fn scenes_round_trip_through_every_format() {
+        round_trip(&crate::game::script::compile::<Store>(STORY).unwrap().scene);
+        for next in &["@main", "@next b", "@next b:2", "@end good"] {
+            round_trip(&crate::game::script::compile::<Store>(&format!("Hi.\n{}\n", next)).unwrap().scene);
+        }
+    }
+
+    #[test]
+    fn updates_arent_read_back_as_sets() {
+        let scene = crate::game::script::compile::<Store>("~ gold += 5\n~ luck += 0.5\n~ gold = 5\n").unwrap().scene;
+        let written = Format::Yaml.to_string(&scene).unwrap();
+        let scene: Scene<Store> = Format::Yaml.from_str(&written).unwrap();
+        let changes = scene.lines[0].to_inner().trigger().unwrap().stats_changes.as_ref().unwrap();
+        assert!(matches!(changes[0].change, crate::game::trigger::ValueChange::UpdateInt(5)), "{}", written);
+        assert!(matches!(changes[1].change, crate::game::trigger::ValueChange::UpdateFloat(_)), "{}", written);
+        assert!(matches!(changes[2].change, crate::game::trigger::ValueChange::SetInt(5)), "{}", written);
+    }
+
+    #[test]
+    fn cfgs_round_trip_through_every_format() {
+        let cfg: crate::game::Cfg<Store> = Format::Yaml
+            .from_str(
+                "\
+scene_list: [a, b]
+scenes: ./scenes/
+saves: ./saves/
+primary_scene: a
+locale: fr
+title_screen:
+  title: Story
+  entries: [new_game, continue, quit]
+  labels: {new_game: Begin}
+pacing:
+  text_speed: 30
+  auto_advance: {base_ms: 500}
+items:
+  coin: {name: Gold coin, stack: 5}
+characters:
+  alice:
+    name: Alice
+    color: red
+    title: Captain
+    vars: {met: false, age: 30}
+    meters:
+      trust:
+        min: 0
+        max: 10
+        thresholds:
+          - above: 3
+            trigger: {scene_change: {name: secret}, stats_changes: [{name: gold, change: {add: 1}}]}
+achievements:
+  rich:
+    title: Rich
+    condition: {Item: {item: coin, count: [{Included: 3}, Unbounded]}}
+endings:
+  good: {title: Good, description: The end.}
+",
+            )
+            .unwrap();
+        round_trip(&cfg);
+    }
+}
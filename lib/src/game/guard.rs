@@ -1,16 +1,17 @@
-use crate::game::state::KeyedStore;
+use crate::game::{item, state::KeyedStore};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(bound = "")]
 pub enum ValueGuard {
     Bool(bool),
     Int((std::ops::Bound<i64>, std::ops::Bound<i64>)),
     Num((std::ops::Bound<f64>, std::ops::Bound<f64>)),
+    Text(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(bound = "")]
 pub struct KeyedGuard<T: KeyedStore> {
     pub name: T::KeyEnum,
@@ -19,10 +20,21 @@ pub struct KeyedGuard<T: KeyedStore> {
     _phantom: std::marker::PhantomData<T>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl<T: KeyedStore> KeyedGuard<T> {
+    pub fn new(name: T::KeyEnum, value: ValueGuard) -> Self {
+        Self {
+            name,
+            value,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(bound = "")]
 pub enum Guard<T: KeyedStore> {
     Value(KeyedGuard<T>),
+    Item(item::ItemGuard),
 
     Not(Box<Guard<T>>),
     And(Vec<Guard<T>>),
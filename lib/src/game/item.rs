@@ -0,0 +1,89 @@
+use crate::game::state::KeyedStore;
+
+use serde::{Deserialize, Serialize};
+use std::ops::{Bound, RangeBounds};
+
+/// An item definition from the game cfg.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Item {
+    /// Display name. Defaults to the item's id.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Most of this item the player can hold at once. Unlimited if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemOp {
+    Give,
+    /// Removes as many as the player has, up to `count`.
+    Take,
+    /// Uses up exactly `count`, which fails if the player doesn't have enough.
+    Consume,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ItemChange {
+    pub item: String,
+    pub op: ItemOp,
+    #[serde(default = "ItemChange::one")]
+    pub count: u32,
+}
+
+impl ItemChange {
+    fn one() -> u32 {
+        1
+    }
+
+    pub fn new(item: String, op: ItemOp, count: u32) -> Self {
+        Self { item, op, count }
+    }
+
+    /// Returns why the change couldn't be made, if it couldn't.
+    pub fn apply<T: KeyedStore>(&self, store: &mut T, def: &Item) -> Result<(), String> {
+        let held = store.item_count(&self.item);
+        let name = def.name.as_deref().unwrap_or(&self.item);
+        let count = match self.op {
+            ItemOp::Give => {
+                let count = held.saturating_add(self.count);
+                def.stack.map_or(count, |stack| count.min(stack))
+            }
+            ItemOp::Take => held.saturating_sub(self.count),
+            ItemOp::Consume if held < self.count => {
+                return Err(format!("You need {} {}, but only have {}.", self.count, name, held));
+            }
+            ItemOp::Consume => held - self.count,
+        };
+        store.set_item_count(&self.item, count);
+        Ok(())
+    }
+}
+
+/// Checks how many of an item the player has. Having at least one by default.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ItemGuard {
+    pub item: String,
+    #[serde(default = "ItemGuard::at_least_one")]
+    pub count: (Bound<u32>, Bound<u32>),
+}
+
+impl ItemGuard {
+    fn at_least_one() -> (Bound<u32>, Bound<u32>) {
+        (Bound::Included(1), Bound::Unbounded)
+    }
+
+    pub fn new(item: String, count: Option<(Bound<u32>, Bound<u32>)>) -> Self {
+        Self {
+            item,
+            count: count.unwrap_or_else(Self::at_least_one),
+        }
+    }
+
+    pub fn check<T: KeyedStore>(&self, store: &T) -> bool {
+        self.count.contains(&store.item_count(&self.item))
+    }
+}
@@ -1,23 +1,59 @@
 use crate::game::state::KeyedStore;
-use super::{guard, trigger, choice, AbsenceError};
+use super::{guard, trigger, choice, item, prompt, AbsenceError};
 
-use serde::{Deserialize, Serialize};
+use serde::{de::Error as _, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(bound = "")]
+/// Written as part of the line they belong to.
+#[derive(Debug)]
 pub struct Choices<T: KeyedStore> {
     pub choices: Vec<choice::Choice<T>>,
     pub default_choice: usize,
+    /// Milliseconds the player has to choose, before `timeout_choice` is picked for them.
+    pub timeout: Option<u64>,
+    /// Choice picked when time runs out. Defaults to `default_choice`.
+    pub timeout_choice: Option<usize>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(bound = "")]
+/// Read as part of `LineOrTrigger`. The fields of `choices` are written alongside the line's own.
+#[derive(Debug)]
 pub struct Line<T: KeyedStore> {
-    #[serde(default)]
     pub speaker: Option<String>,
+    /// In the source language. Used when there's no translation.
     pub text: String,
-    #[serde(flatten)]
+    /// Looks up translations by this instead of by `text`.
+    pub text_id: Option<String>,
     pub choices: Option<Choices<T>>,
+    /// Characters revealed per second, overriding `cfg.pacing.text_speed`.
+    pub speed: Option<u32>,
+    /// Milliseconds to wait before the line starts appearing.
+    pub pause: Option<u64>,
+    /// Asks the player to type something in. Takes precedence over choices.
+    pub prompt: Option<prompt::Prompt<T>>,
+}
+
+// Flattening `choices` would write a map rather than a struct, which RON can't read back as one.
+impl<T: KeyedStore> Serialize for Line<T> {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        fn field<S: SerializeStruct, V: Serialize>(st: &mut S, key: &'static str, value: Option<V>) -> Result<(), S::Error> {
+            match value {
+                Some(_) => st.serialize_field(key, &value),
+                None => st.skip_field(key),
+            }
+        }
+        let choices = self.choices.as_ref();
+        let mut st = s.serialize_struct("Line", 10)?;
+        field(&mut st, "speaker", self.speaker.as_ref())?;
+        st.serialize_field("text", &self.text)?;
+        field(&mut st, "text_id", self.text_id.as_ref())?;
+        field(&mut st, "choices", choices.map(|c| &c.choices))?;
+        field(&mut st, "default_choice", choices.map(|c| c.default_choice))?;
+        field(&mut st, "timeout", choices.and_then(|c| c.timeout))?;
+        field(&mut st, "timeout_choice", choices.and_then(|c| c.timeout_choice))?;
+        field(&mut st, "speed", self.speed)?;
+        field(&mut st, "pause", self.pause)?;
+        field(&mut st, "prompt", self.prompt.as_ref())?;
+        st.end()
+    }
 }
 
 impl<T: KeyedStore> Line<T> {
@@ -25,40 +61,43 @@ impl<T: KeyedStore> Line<T> {
         let cc = if let Some(cc) = self.choices.as_ref() {
             &cc.choices
         } else {
-            return Err(AbsenceError::NotEnough);
+            return Err(AbsenceError::NoChoices);
         };
-        if cc.len() < choice {
+        if cc.len() <= choice {
             return Err(AbsenceError::NotEnough);
         }
-        let mut curr_choice = 0;
-        let mut selected_choice = None;
-        for c in cc {
-            if curr_choice == choice {
-                selected_choice = Some(c);
-                break;
-            }
-            if let Some(g) = c.guard.as_ref() {
-                if store.check_guard(g) {
-                    curr_choice += 1;
-                }
-            } else {
-                curr_choice += 1;
-            }
-        }
-        selected_choice.ok_or(AbsenceError::NotPresentAfterScan)
+        cc
+            .iter()
+            .filter(|c| c.guard.as_ref().is_none_or(|g| store.check_guard(g)))
+            .nth(choice)
+            .ok_or(AbsenceError::NotPresentAfterScan)
     }
 
     pub fn try_get_default_choice(&self, store: &T) -> Result<&choice::Choice<T>, AbsenceError> {
-        let (cc, default_choice) = if let Some(cc) = self.choices.as_ref() {
-            (&cc.choices, cc.default_choice)
+        let default_choice = self.choices.as_ref().map_or(0, |cc| cc.default_choice);
+        self.try_get_choice_at(store, default_choice)
+    }
+
+    pub fn try_get_timeout_choice(&self, store: &T) -> Result<&choice::Choice<T>, AbsenceError> {
+        let timeout_choice = self
+            .choices
+            .as_ref()
+            .map_or(0, |cc| cc.timeout_choice.unwrap_or(cc.default_choice));
+        self.try_get_choice_at(store, timeout_choice)
+    }
+
+    /// Gets the choice at `idx` in all choices, not just the visible ones.
+    fn try_get_choice_at(&self, store: &T, idx: usize) -> Result<&choice::Choice<T>, AbsenceError> {
+        let cc = if let Some(cc) = self.choices.as_ref() {
+            &cc.choices
         } else {
-            return Err(AbsenceError::NotEnough);
+            return Err(AbsenceError::NoChoices);
         };
-        if cc.len() < default_choice {
+        if cc.len() <= idx {
             return Err(AbsenceError::NotEnough);
         }
         cc
-            .get(default_choice)
+            .get(idx)
             .and_then(|c| if c.guard.as_ref().map(|g| store.check_guard(g)).unwrap_or(true) {
                 Some(c)
             } else {
@@ -68,13 +107,124 @@ impl<T: KeyedStore> Line<T> {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Debug)]
 #[serde(bound = "", untagged)]
 pub enum LineOrTrigger<T: KeyedStore> {
     Line(Line<T>),
     Trigger(trigger::Trigger<T>),
 }
 
+/// Lines are told apart from triggers by having `text`. The fields are read one by one, rather
+/// than trying each variant in turn, so that errors point at the field they're in.
+impl<'de, T: KeyedStore> Deserialize<'de> for LineOrTrigger<T> {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(bound = "", deny_unknown_fields)]
+        struct Fields<T: KeyedStore> {
+            #[serde(default)]
+            speaker: Option<String>,
+            // Optional only to tell lines from triggers, so RON doesn't write it as `Some(..)`.
+            #[serde(default, deserialize_with = "some")]
+            text: Option<String>,
+            #[serde(default)]
+            text_id: Option<String>,
+            #[serde(default)]
+            choices: Option<Vec<choice::Choice<T>>>,
+            #[serde(default)]
+            default_choice: Option<usize>,
+            #[serde(default)]
+            timeout: Option<u64>,
+            #[serde(default)]
+            timeout_choice: Option<usize>,
+            #[serde(default)]
+            speed: Option<u32>,
+            #[serde(default)]
+            pause: Option<u64>,
+            #[serde(default)]
+            prompt: Option<prompt::Prompt<T>>,
+            #[serde(default)]
+            stats_changes: Option<Vec<trigger::StatChange<T>>>,
+            #[serde(default)]
+            item_changes: Option<Vec<item::ItemChange>>,
+            #[serde(default)]
+            unlocks: Option<Vec<String>>,
+            #[serde(default)]
+            scene_change: Option<trigger::SceneChange>,
+            #[serde(default)]
+            end: Option<String>,
+        }
+        fn some<'de, D: Deserializer<'de>>(de: D) -> Result<Option<String>, D::Error> {
+            String::deserialize(de).map(Some)
+        }
+        let f = Fields::<T>::deserialize(de)?;
+        let text = match f.text {
+            Some(text) => text,
+            None => {
+                let line_field = [
+                    ("speaker", f.speaker.is_some()),
+                    ("text_id", f.text_id.is_some()),
+                    ("choices", f.choices.is_some()),
+                    ("default_choice", f.default_choice.is_some()),
+                    ("timeout", f.timeout.is_some()),
+                    ("timeout_choice", f.timeout_choice.is_some()),
+                    ("speed", f.speed.is_some()),
+                    ("pause", f.pause.is_some()),
+                    ("prompt", f.prompt.is_some()),
+                ]
+                .iter()
+                .find(|(_, given)| *given)
+                .map(|(field, _)| *field);
+                if let Some(field) = line_field {
+                    return Err(D::Error::custom(format!("`{}` is only for lines, which need `text`.", field)));
+                }
+                return Ok(LineOrTrigger::Trigger(trigger::Trigger {
+                    stats_changes: f.stats_changes,
+                    item_changes: f.item_changes,
+                    unlocks: f.unlocks,
+                    scene_change: f.scene_change,
+                    end: f.end,
+                }));
+            }
+        };
+        let trigger_field = [
+            ("stats_changes", f.stats_changes.is_some()),
+            ("item_changes", f.item_changes.is_some()),
+            ("unlocks", f.unlocks.is_some()),
+            ("scene_change", f.scene_change.is_some()),
+            ("end", f.end.is_some()),
+        ]
+        .iter()
+        .find(|(_, given)| *given)
+        .map(|(field, _)| *field);
+        if let Some(field) = trigger_field {
+            return Err(D::Error::custom(format!(
+                "`{}` is only for triggers, which can't have `text`. Put it on a line of its own.",
+                field,
+            )));
+        }
+        let choices = match (f.choices, f.default_choice) {
+            (Some(choices), Some(default_choice)) => Some(Choices {
+                choices,
+                default_choice,
+                timeout: f.timeout,
+                timeout_choice: f.timeout_choice,
+            }),
+            (None, None) => None,
+            (Some(_), None) => return Err(D::Error::missing_field("default_choice")),
+            (None, Some(_)) => return Err(D::Error::missing_field("choices")),
+        };
+        Ok(LineOrTrigger::Line(Line {
+            speaker: f.speaker,
+            text,
+            text_id: f.text_id,
+            choices,
+            speed: f.speed,
+            pause: f.pause,
+            prompt: f.prompt,
+        }))
+    }
+}
+
 impl<T: KeyedStore> LineOrTrigger<T> {
     pub fn line(&self) -> Option<&Line<T>> {
         if let Self::Line(l) = self {
@@ -95,17 +245,22 @@ impl<T: KeyedStore> LineOrTrigger<T> {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(bound = "")]
 pub struct GuardedLineOrTrigger<T: KeyedStore> {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     guard: Option<guard::Guard<T>>,
     guarded: LineOrTrigger<T>,
 }
 
 impl<T: KeyedStore> GuardedLineOrTrigger<T> {
-    pub fn try_to_inner(&self, store: &T) -> Result<&LineOrTrigger<T>, ()> {
-        if self.guard.as_ref().map_or(false, |g| store.check_guard(g)) {
-            Ok(&self.guarded)
+    pub fn new(guard: Option<guard::Guard<T>>, guarded: LineOrTrigger<T>) -> Self {
+        Self { guard, guarded }
+    }
+
+    /// Returns `None` if the guard hides this line.
+    pub fn try_to_inner(&self, store: &T) -> Option<&LineOrTrigger<T>> {
+        if self.guard.as_ref().is_none_or(|g| store.check_guard(g)) {
+            Some(&self.guarded)
         } else {
-            Err(())
+            None
         }
     }
     // Should only be called if you're absolutely positive that it's okay.
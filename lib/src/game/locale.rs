@@ -0,0 +1,73 @@
+use crate::game::{self, choice::Choice, line::{Line, LineOrTrigger}, KeyedStore};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Translations for one locale, kept as `{locale}.{yaml,json,toml,ron}` in the locales directory.
+/// Text is looked up by its string id if it has one, and by the text itself otherwise.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Table {
+    pub strings: BTreeMap<String, String>,
+}
+
+impl Table {
+    /// Loads the table for `locale`.
+    pub fn load<T: KeyedStore>(cfg: &game::Cfg<T>, locale: &str) -> Result<Self, game::LoadErr> {
+        game::find_and_read_file(cfg.locales_dir().as_path(), locale)
+    }
+
+    /// The table for the locale the game cfg picks, or an empty one to show the source language.
+    pub fn load_active<T: KeyedStore>(cfg: &game::Cfg<T>) -> Result<Self, game::LoadErr> {
+        match cfg.locale.as_ref() {
+            Some(locale) => Self::load(cfg, locale),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Falls back to `text` if there's no translation.
+    pub fn translate<'a>(&'a self, id: Option<&str>, text: &'a str) -> &'a str {
+        self.strings
+            .get(id.unwrap_or(text))
+            .map_or(text, String::as_str)
+    }
+
+    pub fn line<'a, T: KeyedStore>(&'a self, line: &'a Line<T>) -> &'a str {
+        self.translate(line.text_id.as_deref(), &line.text)
+    }
+
+    pub fn choice<'a, T: KeyedStore>(&'a self, choice: &'a Choice<T>) -> &'a str {
+        self.translate(choice.text_id.as_deref(), &choice.text)
+    }
+
+    /// A template to translate from, with every string in every scene of `scene_list`, and the
+    /// name of every character, mapped to the source text.
+    pub fn extract<T: KeyedStore>(cfg: &game::Cfg<T>) -> Result<Self, game::LoadErr> {
+        let mut strings = BTreeMap::new();
+        let mut add = |id: Option<&String>, text: &String| {
+            strings.insert(id.unwrap_or(text).clone(), text.clone());
+        };
+        for character in cfg.characters.values() {
+            add(None, &character.name);
+        }
+        let dir = cfg.root.join(cfg.scenes.as_path());
+        for name in cfg.scene_list.iter() {
+            let scene = game::Scene::<T>::load_from(dir.as_path(), name, &BTreeMap::new())?;
+            for line in scene.lines.iter() {
+                let line = match line.to_inner() {
+                    LineOrTrigger::Line(line) => line,
+                    LineOrTrigger::Trigger(_) => continue,
+                };
+                if let Some(speaker) = line.speaker.as_ref() {
+                    if game::character::find(&cfg.characters, speaker).is_none() {
+                        add(None, speaker);
+                    }
+                }
+                add(line.text_id.as_ref(), &line.text);
+                for choice in line.choices.iter().flat_map(|cc| cc.choices.iter()) {
+                    add(choice.text_id.as_ref(), &choice.text);
+                }
+            }
+        }
+        Ok(Self { strings })
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The screen shown before the story starts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TitleScreen {
+    /// Shown above the entries. Defaults to nothing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    /// Which entries to show, in order.
+    #[serde(default = "TitleEntry::all")]
+    pub entries: Vec<TitleEntry>,
+    /// Text to show for entries, instead of the built-in labels.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<TitleEntry, String>,
+}
+
+impl TitleScreen {
+    pub fn label(&self, entry: TitleEntry) -> &str {
+        self.labels.get(&entry).map_or_else(|| entry.default_label(), String::as_str)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TitleEntry {
+    NewGame,
+    /// Loads the most recent save. Hidden if there are none.
+    Continue,
+    Load,
+    Settings,
+    /// Endings and achievements reached so far.
+    Gallery,
+    Quit,
+}
+
+impl TitleEntry {
+    fn all() -> Vec<Self> {
+        vec![
+            TitleEntry::NewGame,
+            TitleEntry::Continue,
+            TitleEntry::Load,
+            TitleEntry::Settings,
+            TitleEntry::Gallery,
+            TitleEntry::Quit,
+        ]
+    }
+
+    pub fn default_label(self) -> &'static str {
+        match self {
+            TitleEntry::NewGame => "New Game",
+            TitleEntry::Continue => "Continue",
+            TitleEntry::Load => "Load",
+            TitleEntry::Settings => "Settings",
+            TitleEntry::Gallery => "Gallery",
+            TitleEntry::Quit => "Quit",
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::game::state::KeyedStore;
+
+use serde::{Deserialize, Serialize};
+
+/// Asks the player to type in an answer, which is stored as text under `into`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(bound = "")]
+pub struct Prompt<T: KeyedStore> {
+    pub into: T::KeyEnum,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+}
+
+impl<T: KeyedStore> Prompt<T> {
+    pub fn new(into: T::KeyEnum, rules: Vec<Rule>) -> Self {
+        Self { into, rules }
+    }
+
+    /// Returns why the answer was rejected, if it was.
+    pub fn validate(&self, answer: &str) -> Result<(), String> {
+        self.rules.iter().try_for_each(|rule| rule.check(answer))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    MinLength(usize),
+    MaxLength(usize),
+    /// Letters, digits and spaces only.
+    Alphanumeric,
+    /// Digits only.
+    Numeric,
+    OneOf(Vec<String>),
+}
+
+impl Rule {
+    pub fn check(&self, answer: &str) -> Result<(), String> {
+        let len = answer.chars().count();
+        match self {
+            Rule::MinLength(min) if len < *min => Err(match min {
+                1 => "The answer can't be empty.".to_owned(),
+                _ => format!("The answer needs at least {} characters.", min),
+            }),
+            Rule::MaxLength(max) if len > *max => {
+                Err(format!("The answer can't be longer than {} characters.", max))
+            }
+            Rule::Alphanumeric if !answer.chars().all(|c| c.is_alphanumeric() || c == ' ') => {
+                Err("The answer can only have letters, numbers and spaces.".to_owned())
+            }
+            Rule::Numeric if !answer.chars().all(|c| c.is_ascii_digit()) => {
+                Err("The answer can only have digits.".to_owned())
+            }
+            Rule::OneOf(options) if !options.iter().any(|o| o == answer) => {
+                Err(format!("The answer must be one of: {}.", options.join(", ")))
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,1040 @@
+//! Screenplay-style scene scripts, for when YAML gets too verbose.
+//!
+//! ```text
+//! # Comments take up a whole line, and start with `#`.
+//! # The scene to go to afterwards. `@main` or `@main 3` goes to the primary scene instead.
+//! @next b:2
+//! ALICE: Hello there.
+//!     Indented lines continue the text of the line above.
+//! This line has no speaker.
+//! # Speakers are a single word, such as a character's id, so the next line has none either.
+//! Then it happened: boom.
+//! \Note: a leading backslash stops `Note` from being read as the speaker.
+//! # Choices attach to the line above, and `**` marks the default choice.
+//! * Apologize. -> b:4
+//!     ~ guilt += 1
+//! ** Say nothing.
+//! * [if bossy >= 2 && !humble] Be privileged.
+//! # Guards apply to the following line, trigger or choice, or can be put in front of it.
+//! [if bossy > 4]
+//! BOB: Why can't you just apologize?
+//! [if humble] BOB: Thanks.
+//! # `@pause` (milliseconds) and `@speed` (characters per second) apply to the next line.
+//! @pause 1500
+//! @speed 5
+//! ALICE: ...Fine.
+//! # `@timeout` gives the choices of the next line a time limit, in milliseconds. Optionally, the
+//! # choice to pick when time runs out can be given (counting from 1), instead of the default one.
+//! @timeout 5000 2
+//! BOB: Run!
+//! ** Left.
+//! * Right.
+//! # `@id` gives the next line or choice a string id, to look up translations by instead of its text.
+//! @id guard_halt
+//! GUARD: Halt!
+//! # `?` asks the player to type something in, stored as text. Rules after the `:` are
+//! # optional, and are any of `min N`, `max N`, `alphanumeric`, `numeric` and `one of a | b`.
+//! GUARD: Password?
+//! ? password: min 1, max 16
+//! [if password == "swordfish"] GUARD: Go on in.
+//! # Items are given, taken (as many as the player has, up to the count) or consumed (failing if
+//! # the player has too few), one at a time unless a count is given.
+//! [if has coin >= 3] ~ consume coin 3
+//! ~ give potion
+//! # Achievements from the game cfg can be unlocked directly.
+//! ~ unlock generous
+//! # Consecutive stat and item changes are merged into one trigger, which a scene change ends.
+//! # Guarded ones get a trigger of their own, so only the consumed coins above depend on the guard.
+//! ~ bossy += 1
+//! ~ humble = false
+//! -> c
+//! # Endings from the game cfg end the story, either from a trigger or choice with `-> @end id`,
+//! # or as the scene's `@end id`, instead of `@next`.
+//! ```
+//!
+//! Scene change targets are `scene` or `scene:line`, where `line` is the index of the line in the
+//! target scene. Stat changes support `=`, `+=` and `-=` with booleans, integers or floats, as well
+//! as `~ toggle name`. Guards support `name` (true), `!`, `&&`, `||`, parentheses and comparisons
+//! (`==`, `!=`, `<`, `<=`, `>`, `>=`) against booleans, integers or floats. Text can be compared
+//! with `==` and `!=` against quoted strings. `has item` checks that the player holds the item, and
+//! `has item >= 2` (or any other comparison) checks how many.
+
+use crate::game::{
+    choice::Choice,
+    guard::{Guard, KeyedGuard, ValueGuard},
+    item::{ItemChange, ItemGuard, ItemOp},
+    line::{Choices, GuardedLineOrTrigger, Line, LineOrTrigger},
+    prompt::{Prompt, Rule},
+    state::KeyedStore,
+    trigger::{SceneChange, StatChange, Trigger, ValueChange},
+    diagnostic::Location,
+    ending::End,
+    MainOrSceneChange, Scene,
+};
+use serde::Deserialize;
+use std::ops::Bound;
+
+pub const EXTENSION: &str = "seren";
+
+#[derive(Debug)]
+pub struct Err {
+    pub span: Location,
+    pub msg: String,
+}
+
+impl Err {
+    fn new(span: Location, msg: impl Into<String>) -> Self {
+        Self {
+            span,
+            msg: msg.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Err {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}: {}", self.span.line, self.span.column, self.msg)
+    }
+}
+impl std::error::Error for Err {}
+
+/// A compiled script, along with where each of the scene's lines came from.
+#[derive(Debug)]
+pub struct Compiled<T: KeyedStore> {
+    pub scene: Scene<T>,
+    pub locations: Vec<Location>,
+}
+
+struct Item<T: KeyedStore> {
+    guard: Option<Guard<T>>,
+    inner: LineOrTrigger<T>,
+    span: Location,
+}
+
+#[derive(PartialEq, Eq)]
+enum Open {
+    Nothing,
+    /// The last item is a line without choices, so indented text continues it.
+    Text,
+    /// The last item is an unguarded trigger without a scene change, so more stat changes can be
+    /// merged in.
+    Trigger,
+    /// The last choice can take indented stat changes and scene changes.
+    Choice,
+}
+
+pub fn compile<T: KeyedStore>(src: &str) -> Result<Compiled<T>, Err> {
+    let mut items: Vec<Item<T>> = vec![];
+    let mut next_scene = None;
+    let mut pending_guard: Option<(Guard<T>, Location)> = None;
+    let mut has_default_choice = false;
+    let mut open = Open::Nothing;
+    let mut pending_pause: Option<(u64, Location)> = None;
+    let mut pending_speed: Option<(u32, Location)> = None;
+    let mut pending_id: Option<(String, Location)> = None;
+    let mut pending_timeout: Option<(u64, Option<usize>, Location)> = None;
+    // The timeout given to the last line, until its choices show up.
+    let mut line_timeout: Option<(u64, Option<usize>, Location)> = None;
+
+    for (raw, line_num) in src.lines().zip(1..) {
+        let trimmed = raw.trim();
+        let span = Location {
+            line: line_num,
+            column: raw.len() - raw.trim_start().len() + 1,
+        };
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            if open == Open::Text {
+                open = Open::Nothing;
+            }
+            continue;
+        }
+
+        if raw.starts_with(char::is_whitespace) {
+            match open {
+                Open::Choice if trimmed.starts_with('~') || trimmed.starts_with("->") => {
+                    let choice = last_choice(&mut items);
+                    let trigger = choice.trigger.get_or_insert_with(empty_trigger);
+                    add_to_trigger(trigger, trimmed, span)?;
+                }
+                Open::Text => {
+                    if let Some(LineOrTrigger::Line(line)) = items.last_mut().map(|i| &mut i.inner) {
+                        line.text.push('\n');
+                        line.text.push_str(trimmed);
+                    }
+                }
+                _ => return Err(Err::new(span, "Indented line doesn't continue a line or choice.")),
+            }
+            continue;
+        }
+
+        let (guard, rest, rest_span) = split_guard::<T>(trimmed, span)?;
+        let guard = match (guard, rest.is_empty()) {
+            (Some(g), true) => {
+                if pending_guard.is_some() {
+                    return Err(Err::new(span, "Two guards in a row. Combine them with `&&`."));
+                }
+                pending_guard = Some((g, span));
+                continue;
+            }
+            (Some(g), false) => {
+                if pending_guard.is_some() {
+                    return Err(Err::new(span, "Line has a guard, but a guard was already given above."));
+                }
+                Some(g)
+            }
+            (None, _) => pending_guard.take().map(|(g, _)| g),
+        };
+
+        if let Some(directive) = rest.strip_prefix('@') {
+            if guard.is_some() {
+                return Err(Err::new(span, "Directives can't be guarded."));
+            }
+            match parse_directive(directive.trim(), rest_span)? {
+                Directive::Next(_) if next_scene.is_some() => {
+                    return Err(Err::new(span, "The next scene was already set."));
+                }
+                Directive::Next(next) => next_scene = Some(next),
+                Directive::Pause(ms) => pending_pause = Some((ms, span)),
+                Directive::Speed(speed) => pending_speed = Some((speed, span)),
+                Directive::Timeout(ms, choice) => pending_timeout = Some((ms, choice, span)),
+                Directive::Id(_) if pending_id.is_some() => {
+                    return Err(Err::new(span, "Two string ids in a row."));
+                }
+                Directive::Id(id) => pending_id = Some((id, span)),
+            }
+            open = Open::Nothing;
+        } else if let Some(choice) = rest.strip_prefix('*') {
+            let (is_default, choice) = match choice.strip_prefix('*') {
+                Some(choice) => (true, choice),
+                None => (false, choice),
+            };
+            let choice_span = rest_span.offset(rest.len() - choice.trim_start().len());
+            let (inline_guard, choice, choice_span) = split_guard::<T>(choice.trim(), choice_span)?;
+            let guard = match (guard, inline_guard) {
+                (Some(_), Some(_)) => return Err(Err::new(span, "Choice has two guards. Combine them with `&&`.")),
+                (g, None) | (None, g) => g,
+            };
+            let choices = match items.last_mut().map(|i| &mut i.inner) {
+                Some(LineOrTrigger::Line(Line { prompt: Some(_), .. })) => {
+                    return Err(Err::new(span, "Lines that ask for an answer can't have choices."))
+                }
+                Some(LineOrTrigger::Line(line)) => line.choices.get_or_insert_with(|| {
+                    has_default_choice = false;
+                    let (timeout, timeout_choice) = line_timeout
+                        .take()
+                        .map_or((None, None), |(ms, choice, _)| (Some(ms), choice));
+                    Choices {
+                        choices: vec![],
+                        default_choice: 0,
+                        timeout,
+                        timeout_choice,
+                    }
+                }),
+                _ => return Err(Err::new(span, "Choices must come right after a line.")),
+            };
+            if is_default {
+                if has_default_choice {
+                    return Err(Err::new(span, "Line already has a default choice."));
+                }
+                has_default_choice = true;
+                choices.default_choice = choices.choices.len();
+            }
+            let mut choice = parse_choice(choice, choice_span, guard)?;
+            choice.text_id = pending_id.take().map(|(id, _)| id);
+            choices.choices.push(choice);
+            open = Open::Choice;
+        } else if let Some(prompt) = rest.strip_prefix('?') {
+            if guard.is_some() {
+                return Err(Err::new(span, "Prompts can't be guarded. Guard the line instead."));
+            }
+            let prompt_span = rest_span.offset(rest.len() - prompt.trim_start().len());
+            match items.last_mut().map(|i| &mut i.inner) {
+                Some(LineOrTrigger::Line(line)) if open == Open::Text && line.prompt.is_none() => {
+                    line.prompt = Some(parse_prompt(prompt.trim(), prompt_span)?);
+                }
+                _ => return Err(Err::new(span, "Prompts must come right after a line without choices.")),
+            }
+            open = Open::Nothing;
+        } else if rest.starts_with('~') || rest.starts_with("->") {
+            let merge = open == Open::Trigger && guard.is_none();
+            if !merge {
+                items.push(Item {
+                    guard,
+                    inner: LineOrTrigger::Trigger(empty_trigger()),
+                    span,
+                });
+            }
+            if let Some(Item { guard, inner: LineOrTrigger::Trigger(trigger), .. }) = items.last_mut() {
+                add_to_trigger(trigger, rest, rest_span)?;
+                let ends = trigger.scene_change.is_some() || trigger.end.is_some();
+                open = if ends || guard.is_some() { Open::Nothing } else { Open::Trigger };
+            }
+        } else {
+            if let Some((_, _, span)) = line_timeout {
+                return Err(Err::new(span, "Timeout applies to a line without choices."));
+            }
+            line_timeout = pending_timeout.take();
+            let mut line = parse_line(rest);
+            line.pause = pending_pause.take().map(|(ms, _)| ms);
+            line.speed = pending_speed.take().map(|(speed, _)| speed);
+            line.text_id = pending_id.take().map(|(id, _)| id);
+            items.push(Item {
+                guard,
+                inner: LineOrTrigger::Line(line),
+                span,
+            });
+            open = Open::Text;
+        }
+    }
+
+    if let Some((_, span)) = pending_guard {
+        return Err(Err::new(span, "Guard at the end of the script doesn't guard anything."));
+    }
+    if let Some(span) = pending_pause.map(|(_, s)| s).or(pending_speed.map(|(_, s)| s)) {
+        return Err(Err::new(span, "Pacing at the end of the script doesn't apply to any line."));
+    }
+    if let Some((_, span)) = pending_id {
+        return Err(Err::new(span, "String id at the end of the script doesn't apply to any line or choice."));
+    }
+    if let Some((_, _, span)) = line_timeout.or(pending_timeout) {
+        return Err(Err::new(span, "Timeout applies to a line without choices."));
+    }
+
+    let locations = items.iter().map(|i| i.span).collect();
+    let lines = items
+        .into_iter()
+        .map(|i| GuardedLineOrTrigger::new(i.guard, i.inner))
+        .collect();
+    Ok(Compiled {
+        scene: Scene { lines, next_scene },
+        locations,
+    })
+}
+
+/// Parses a standalone guard expression, as used in `[if ...]`.
+pub fn parse_guard<T: KeyedStore>(expr: &str) -> Result<Guard<T>, Err> {
+    guard_at(expr, Location { line: 1, column: 1 })
+}
+
+/// Parses a standalone trigger, with a `~ change` or `-> scene` on each line.
+pub fn parse_trigger<T: KeyedStore>(src: &str) -> Result<Trigger<T>, Err> {
+    let mut trigger = empty_trigger();
+    for (raw, line_num) in src.lines().zip(1..) {
+        let trimmed = raw.trim();
+        let span = Location {
+            line: line_num,
+            column: raw.len() - raw.trim_start().len() + 1,
+        };
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !(trimmed.starts_with('~') || trimmed.starts_with("->")) {
+            return Err(Err::new(span, "Expected a `~ change` or a `-> scene`."));
+        }
+        add_to_trigger(&mut trigger, trimmed, span)?;
+    }
+    Ok(trigger)
+}
+
+fn empty_trigger<T: KeyedStore>() -> Trigger<T> {
+    Trigger {
+        stats_changes: None,
+        item_changes: None,
+        unlocks: None,
+        scene_change: None,
+        end: None,
+    }
+}
+
+fn last_choice<T: KeyedStore>(items: &mut [Item<T>]) -> &mut Choice<T> {
+    match items.last_mut().map(|i| &mut i.inner) {
+        Some(LineOrTrigger::Line(Line { choices: Some(choices), .. })) => choices
+            .choices
+            .last_mut()
+            .expect("Choices are only created when a choice is pushed."),
+        _ => unreachable!("Only called when the last item is a line with choices."),
+    }
+}
+
+fn split_guard<T: KeyedStore>(s: &str, span: Location) -> Result<(Option<Guard<T>>, &str, Location), Err> {
+    if let Some(after) = s.strip_prefix("[if ") {
+        let end = after
+            .find(']')
+            .ok_or_else(|| Err::new(span, "Guard is missing a closing `]`."))?;
+        let guard = guard_at(&after[..end], span.offset(4))?;
+        let rest = after[end + 1..].trim_start();
+        Ok((Some(guard), rest, span.offset(s.len() - rest.len())))
+    } else {
+        Ok((None, s, span))
+    }
+}
+
+fn parse_line<T: KeyedStore>(s: &str) -> Line<T> {
+    let (speaker, text) = if let Some(narration) = s.strip_prefix('\\') {
+        (None, narration)
+    } else {
+        match s.find(':') {
+            Some(idx)
+                if idx > 0
+                    && s[..idx].chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+                    && s[idx + 1..].chars().next().is_none_or(char::is_whitespace) =>
+            {
+                (Some(s[..idx].trim().to_owned()), s[idx + 1..].trim_start())
+            }
+            _ => (None, s),
+        }
+    };
+    Line {
+        speaker,
+        text: text.to_owned(),
+        text_id: None,
+        choices: None,
+        speed: None,
+        pause: None,
+        prompt: None,
+    }
+}
+
+fn parse_prompt<T: KeyedStore>(s: &str, span: Location) -> Result<Prompt<T>, Err> {
+    let (name, rules) = match s.find(':') {
+        Some(idx) => (s[..idx].trim(), &s[idx + 1..]),
+        None => (s, ""),
+    };
+    let into = key::<T>(name, span)?;
+    let rules = rules
+        .split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(|r| {
+            let (rule, arg) = match r.find(char::is_whitespace) {
+                Some(idx) => (&r[..idx], r[idx..].trim()),
+                None => (r, ""),
+            };
+            let count = || {
+                arg.parse::<usize>()
+                    .map_err(|_| Err::new(span, format!("`{}` needs a number of characters.", rule)))
+            };
+            match rule {
+                "min" => count().map(Rule::MinLength),
+                "max" => count().map(Rule::MaxLength),
+                "alphanumeric" => Ok(Rule::Alphanumeric),
+                "numeric" => Ok(Rule::Numeric),
+                "one" => match arg.strip_prefix("of") {
+                    Some(options) => Ok(Rule::OneOf(options.split('|').map(|o| o.trim().to_owned()).collect())),
+                    None => Err(Err::new(span, "Expected `one of a | b`.")),
+                },
+                _ => Err(Err::new(span, format!("Unknown prompt rule `{}`.", rule))),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Prompt::new(into, rules))
+}
+
+fn parse_choice<T: KeyedStore>(s: &str, span: Location, guard: Option<Guard<T>>) -> Result<Choice<T>, Err> {
+    let (text, trigger) = match s.rfind("->") {
+        Some(idx) => {
+            let mut trigger = empty_trigger();
+            add_to_trigger(&mut trigger, &s[idx..], span.offset(idx))?;
+            (s[..idx].trim(), Some(trigger))
+        }
+        None => (s.trim(), None),
+    };
+    if text.is_empty() {
+        return Err(Err::new(span, "Choice is missing its text."));
+    }
+    Ok(Choice {
+        guard,
+        text: text.to_owned(),
+        text_id: None,
+        trigger,
+    })
+}
+
+/// Adds a `~ stat change`, `~ item change`, `-> scene` or `-> @end ending` to the trigger.
+fn add_to_trigger<T: KeyedStore>(trigger: &mut Trigger<T>, s: &str, span: Location) -> Result<(), Err> {
+    if let Some(target) = s.strip_prefix("->") {
+        if trigger.scene_change.is_some() || trigger.end.is_some() {
+            return Err(Err::new(span, "Scene change was already given."));
+        }
+        let target_span = span.offset(s.len() - target.trim_start().len());
+        if let Some(end) = target.trim().strip_prefix("@end") {
+            let end = end.trim();
+            if !is_ident(end) {
+                return Err(Err::new(target_span, "Ending must be an ending id."));
+            }
+            trigger.end = Some(end.to_owned());
+            return Ok(());
+        }
+        let (name, line) = parse_target(target.trim(), target_span)?;
+        trigger.scene_change = Some(SceneChange::new(name, line));
+    } else if let Some(change) = s.strip_prefix('~') {
+        let change_span = span.offset(s.len() - change.trim_start().len());
+        if let Some(id) = change.trim().strip_prefix("unlock ") {
+            let id = id.trim();
+            if !is_ident(id) {
+                return Err(Err::new(change_span, format!("`{}` isn't a valid achievement id.", id)));
+            }
+            trigger.unlocks.get_or_insert_with(Vec::new).push(id.to_owned());
+        } else if let Some(item_change) = parse_item_change(change.trim(), change_span)? {
+            trigger.item_changes.get_or_insert_with(Vec::new).push(item_change);
+        } else {
+            trigger
+                .stats_changes
+                .get_or_insert_with(Vec::new)
+                .push(parse_stat_change(change.trim(), change_span)?);
+        }
+    }
+    Ok(())
+}
+
+fn parse_target(s: &str, span: Location) -> Result<(String, Option<usize>), Err> {
+    let (name, line) = match s.find(':') {
+        Some(idx) => {
+            let line = s[idx + 1..]
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| Err::new(span.offset(idx + 1), "Target line must be a line index."))?;
+            (s[..idx].trim(), Some(line))
+        }
+        None => (s, None),
+    };
+    if !is_ident(name) {
+        return Err(Err::new(span, format!("`{}` isn't a valid scene name.", name)));
+    }
+    Ok((name.to_owned(), line))
+}
+
+enum Directive {
+    Next(MainOrSceneChange),
+    Pause(u64),
+    Speed(u32),
+    /// Milliseconds, and the index of the choice to pick.
+    Timeout(u64, Option<usize>),
+    /// String id for the next line or choice.
+    Id(String),
+}
+
+fn parse_directive(s: &str, span: Location) -> Result<Directive, Err> {
+    let (name, arg) = match s.find(char::is_whitespace) {
+        Some(idx) => (&s[..idx], s[idx..].trim()),
+        None => (s, ""),
+    };
+    let arg_span = span.offset(1 + s.len() - arg.len());
+    match name {
+        "next" => {
+            let (scene, line) = parse_target(arg, arg_span)?;
+            Ok(Directive::Next(MainOrSceneChange::SceneChange(SceneChange::new(scene, line))))
+        }
+        "end" if is_ident(arg) => Ok(Directive::Next(MainOrSceneChange::End(End { end: arg.to_owned() }))),
+        "end" => Err(Err::new(arg_span, "Ending must be an ending id.")),
+        "main" if arg.is_empty() => Ok(Directive::Next(MainOrSceneChange::Main(None))),
+        "main" => arg
+            .parse::<usize>()
+            .map(|line| Directive::Next(MainOrSceneChange::Main(Some(line))))
+            .map_err(|_| Err::new(arg_span, "Target line must be a line index.")),
+        "pause" => arg
+            .parse::<u64>()
+            .map(Directive::Pause)
+            .map_err(|_| Err::new(arg_span, "Pause must be a number of milliseconds.")),
+        "timeout" => {
+            let mut args = arg.split_whitespace();
+            let ms = args
+                .next()
+                .and_then(|ms| ms.parse::<u64>().ok())
+                .ok_or_else(|| Err::new(arg_span, "Timeout must be a number of milliseconds."))?;
+            let choice = match args.next() {
+                Some(choice) => match choice.parse::<usize>() {
+                    Ok(choice) if choice > 0 => Some(choice - 1),
+                    _ => return Err(Err::new(arg_span, "Timeout choice must be a choice number, counting from 1.")),
+                },
+                None => None,
+            };
+            Ok(Directive::Timeout(ms, choice))
+        }
+        "id" if !arg.is_empty() && !arg.contains(char::is_whitespace) => Ok(Directive::Id(arg.to_owned())),
+        "id" => Err(Err::new(arg_span, "String id must be a single word.")),
+        "speed" => arg
+            .parse::<u32>()
+            .map(Directive::Speed)
+            .map_err(|_| Err::new(arg_span, "Speed must be a number of characters per second.")),
+        _ => Err(Err::new(span, format!("Unknown directive `@{}`.", name))),
+    }
+}
+
+fn parse_stat_change<T: KeyedStore>(s: &str, span: Location) -> Result<StatChange<T>, Err> {
+    if let Some(name) = s.strip_prefix("toggle ") {
+        let name = name.trim();
+        let key = key::<T>(name, span.offset(s.len() - name.len()))?;
+        return Ok(StatChange::new(key, ValueChange::Custom("toggleBool".to_owned())));
+    }
+    let (idx, op) = ["+=", "-=", "="]
+        .iter()
+        .find_map(|op| s.find(op).map(|idx| (idx, *op)))
+        .ok_or_else(|| Err::new(span, "Stat change needs one of `=`, `+=` or `-=`."))?;
+    let key = key::<T>(s[..idx].trim(), span)?;
+    let value_str = s[idx + op.len()..].trim();
+    let value_span = span.offset(s.len() - value_str.len());
+    let change = match (op, parse_literal(value_str, value_span)?) {
+        ("=", Literal::Bool(b)) => ValueChange::SetBool(b),
+        ("=", Literal::Int(i)) => ValueChange::SetInt(i),
+        ("=", Literal::Num(n)) => ValueChange::SetFloat(n),
+        ("+=", Literal::Int(i)) => ValueChange::UpdateInt(i),
+        ("+=", Literal::Num(n)) => ValueChange::UpdateFloat(n),
+        ("-=", Literal::Int(i)) => ValueChange::UpdateInt(
+            i.checked_neg()
+                .ok_or_else(|| Err::new(value_span, format!("Can't take away {}, as it's too large.", i)))?,
+        ),
+        ("-=", Literal::Num(n)) => ValueChange::UpdateFloat(-n),
+        (_, Literal::Bool(_)) => {
+            return Err(Err::new(value_span, format!("Can't use `{}` with a boolean.", op)))
+        }
+        (_, Literal::Text(_)) => {
+            return Err(Err::new(value_span, "Text can only be set by prompts."))
+        }
+        _ => unreachable!("All operators are covered."),
+    };
+    Ok(StatChange::new(key, change))
+}
+
+/// Parses `give item`, `take item 2` and the like. Returns `None` for anything that isn't one, such
+/// as a stat named `give`.
+fn parse_item_change(s: &str, span: Location) -> Result<Option<ItemChange>, Err> {
+    let mut words = s.split_whitespace();
+    let op = match words.next() {
+        Some("give") => ItemOp::Give,
+        Some("take") => ItemOp::Take,
+        Some("consume") => ItemOp::Consume,
+        _ => return Ok(None),
+    };
+    let item = match words.next() {
+        Some(item) if !s.contains('=') => item,
+        _ => return Ok(None),
+    };
+    let item_span = span.offset(s.find(item).expect("Taken from `s`."));
+    if !is_ident(item) {
+        return Err(Err::new(item_span, format!("`{}` isn't a valid item name.", item)));
+    }
+    let count = match words.next() {
+        Some(count) => match count.parse::<u32>() {
+            Ok(count) if count > 0 => count,
+            _ => return Err(Err::new(item_span.offset(item.len()), "Item count must be a positive integer.")),
+        },
+        None => 1,
+    };
+    if words.next().is_some() {
+        return Err(Err::new(span, "Unexpected trailing input in item change."));
+    }
+    Ok(Some(ItemChange::new(item.to_owned(), op, count)))
+}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+}
+
+fn key<T: KeyedStore>(name: &str, span: Location) -> Result<T::KeyEnum, Err> {
+    use serde::de::{value, IntoDeserializer};
+    if !is_ident(name) {
+        return Err(Err::new(span, format!("`{}` isn't a valid stat name.", name)));
+    }
+    T::KeyEnum::deserialize(IntoDeserializer::<value::Error>::into_deserializer(name))
+        .map_err(|e| Err::new(span, format!("`{}` isn't a valid stat name: {}", name, e)))
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Bool(bool),
+    Int(i64),
+    Num(f64),
+    Text(String),
+}
+
+fn parse_literal(s: &str, span: Location) -> Result<Literal, Err> {
+    match s {
+        "true" => Some(Literal::Bool(true)),
+        "false" => Some(Literal::Bool(false)),
+        _ if s.len() >= 2 && s.starts_with('"') && s.ends_with('"') => {
+            Some(Literal::Text(s[1..s.len() - 1].to_owned()))
+        }
+        _ if s.contains('.') => s.parse().map(Literal::Num).ok(),
+        _ => s.parse().map(Literal::Int).ok(),
+    }
+    .ok_or_else(|| Err::new(span, format!("`{}` isn't a boolean, a number or quoted text.", s)))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Word(&'a str),
+    Op(&'static str),
+}
+
+const OPS: [&str; 11] = ["&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")"];
+
+fn tokenize(expr: &str, span: Location) -> Result<Vec<(Token<'_>, Location)>, Err> {
+    let mut tokens = vec![];
+    let mut idx = 0;
+    while idx < expr.len() {
+        let rest = &expr[idx..];
+        let c = rest.chars().next().expect("Not at the end.");
+        if c.is_whitespace() {
+            idx += c.len_utf8();
+        } else if let Some(op) = OPS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push((Token::Op(op), span.offset(idx)));
+            idx += op.len();
+        } else if c == '"' {
+            let len = rest[1..]
+                .find('"')
+                .ok_or_else(|| Err::new(span.offset(idx), "Text is missing its closing `\"`."))?
+                + 2;
+            tokens.push((Token::Word(&rest[..len]), span.offset(idx)));
+            idx += len;
+        } else if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '-'))
+                .unwrap_or(rest.len());
+            tokens.push((Token::Word(&rest[..len]), span.offset(idx)));
+            idx += len;
+        } else {
+            return Err(Err::new(span.offset(idx), format!("Unexpected `{}` in guard.", c)));
+        }
+    }
+    Ok(tokens)
+}
+
+struct GuardParser<'a> {
+    tokens: Vec<(Token<'a>, Location)>,
+    idx: usize,
+    end: Location,
+}
+
+impl<'a> GuardParser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.idx).map(|(t, _)| t)
+    }
+
+    fn span(&self) -> Location {
+        self.tokens.get(self.idx).map_or(self.end, |(_, s)| *s)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or<T: KeyedStore>(&mut self) -> Result<Guard<T>, Err> {
+        let mut gg = vec![self.and()?];
+        while self.eat("||") {
+            gg.push(self.and()?);
+        }
+        Ok(if gg.len() == 1 { gg.remove(0) } else { Guard::Or(gg) })
+    }
+
+    fn and<T: KeyedStore>(&mut self) -> Result<Guard<T>, Err> {
+        let mut gg = vec![self.unary()?];
+        while self.eat("&&") {
+            gg.push(self.unary()?);
+        }
+        Ok(if gg.len() == 1 { gg.remove(0) } else { Guard::And(gg) })
+    }
+
+    fn unary<T: KeyedStore>(&mut self) -> Result<Guard<T>, Err> {
+        if self.eat("!") {
+            Ok(Guard::Not(Box::new(self.unary()?)))
+        } else if self.eat("(") {
+            let g = self.or()?;
+            if !self.eat(")") {
+                return Err(Err::new(self.span(), "Expected a closing `)`."));
+            }
+            Ok(g)
+        } else {
+            self.comparison()
+        }
+    }
+
+    fn comparison<T: KeyedStore>(&mut self) -> Result<Guard<T>, Err> {
+        let name_span = self.span();
+        let name = match self.peek() {
+            Some(Token::Word(w)) => *w,
+            _ => return Err(Err::new(name_span, "Expected a stat name.")),
+        };
+        self.idx += 1;
+        if name == "has" {
+            if let Some(Token::Word(item)) = self.peek() {
+                let item = *item;
+                self.idx += 1;
+                return self.item_comparison(item, name_span);
+            }
+        }
+        let key = key::<T>(name, name_span)?;
+        let op = match self.peek() {
+            Some(Token::Op(op)) if ["==", "!=", "<", "<=", ">", ">="].contains(op) => *op,
+            _ => return Ok(Guard::Value(KeyedGuard::new(key, ValueGuard::Bool(true)))),
+        };
+        self.idx += 1;
+        let value_span = self.span();
+        let value = match self.peek() {
+            Some(Token::Word(w)) => parse_literal(w, value_span)?,
+            _ => return Err(Err::new(value_span, "Expected a value to compare against.")),
+        };
+        self.idx += 1;
+        let value = match value {
+            Literal::Bool(b) if op == "==" => ValueGuard::Bool(b),
+            Literal::Bool(b) if op == "!=" => ValueGuard::Bool(!b),
+            Literal::Bool(_) => {
+                return Err(Err::new(value_span, format!("Can't use `{}` with a boolean.", op)))
+            }
+            Literal::Int(i) => ValueGuard::Int(bounds(op, i)),
+            Literal::Num(n) => ValueGuard::Num(bounds(op, n)),
+            Literal::Text(t) if op == "==" || op == "!=" => ValueGuard::Text(t),
+            Literal::Text(_) => {
+                return Err(Err::new(value_span, format!("Can't use `{}` with text.", op)))
+            }
+        };
+        let negate = op == "!=" && !matches!(value, ValueGuard::Bool(_));
+        let guard = Guard::Value(KeyedGuard::new(key, value));
+        Ok(if negate { Guard::Not(Box::new(guard)) } else { guard })
+    }
+
+    /// The rest of `has item`, with an optional comparison against the count.
+    fn item_comparison<T: KeyedStore>(&mut self, item: &str, span: Location) -> Result<Guard<T>, Err> {
+        if !is_ident(item) {
+            return Err(Err::new(span, format!("`{}` isn't a valid item name.", item)));
+        }
+        let op = match self.peek() {
+            Some(Token::Op(op)) if ["==", "!=", "<", "<=", ">", ">="].contains(op) => *op,
+            _ => return Ok(Guard::Item(ItemGuard::new(item.to_owned(), None))),
+        };
+        self.idx += 1;
+        let value_span = self.span();
+        let count = match self.peek() {
+            Some(Token::Word(w)) => w
+                .parse::<u32>()
+                .map_err(|_| Err::new(value_span, "Item count must be a non-negative integer."))?,
+            _ => return Err(Err::new(value_span, "Expected a count to compare against.")),
+        };
+        self.idx += 1;
+        let guard = Guard::Item(ItemGuard::new(item.to_owned(), Some(bounds(op, count))));
+        Ok(if op == "!=" { Guard::Not(Box::new(guard)) } else { guard })
+    }
+}
+
+fn bounds<N: Copy>(op: &str, n: N) -> (Bound<N>, Bound<N>) {
+    match op {
+        "<" => (Bound::Unbounded, Bound::Excluded(n)),
+        "<=" => (Bound::Unbounded, Bound::Included(n)),
+        ">" => (Bound::Excluded(n), Bound::Unbounded),
+        ">=" => (Bound::Included(n), Bound::Unbounded),
+        // `!=` is handled by negating `==`.
+        _ => (Bound::Included(n), Bound::Included(n)),
+    }
+}
+
+fn guard_at<T: KeyedStore>(expr: &str, span: Location) -> Result<Guard<T>, Err> {
+    let mut parser = GuardParser {
+        tokens: tokenize(expr, span)?,
+        idx: 0,
+        end: span.offset(expr.len()),
+    };
+    let guard = parser.or()?;
+    if parser.idx < parser.tokens.len() {
+        return Err(Err::new(parser.span(), "Unexpected trailing input in guard."));
+    }
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::default::Store;
+
+    fn at(line: usize, column: usize) -> Location {
+        Location { line, column }
+    }
+
+    #[test]
+    fn lines_take_a_one_word_speaker() {
+        let line = parse_line::<Store>("ALICE: Hello there.");
+        assert_eq!(line.speaker.as_deref(), Some("ALICE"));
+        assert_eq!(line.text, "Hello there.");
+        let line = parse_line::<Store>("old_man-2: Hm.");
+        assert_eq!(line.speaker.as_deref(), Some("old_man-2"));
+    }
+
+    #[test]
+    fn lines_without_a_speaker_keep_their_colons() {
+        for text in ["Then it happened: boom.", "Ratio:3", ": nothing before it", "Just text."] {
+            let line = parse_line::<Store>(text);
+            assert_eq!(line.speaker, None, "{:?}", text);
+            assert_eq!(line.text, text);
+        }
+        let line = parse_line::<Store>("\\Note: not a speaker.");
+        assert_eq!(line.speaker, None);
+        assert_eq!(line.text, "Note: not a speaker.");
+    }
+
+    #[test]
+    fn guards_combine_comparisons() {
+        let guard = parse_guard::<Store>("gold >= 5 && !angry").unwrap();
+        let parts = match guard {
+            Guard::And(parts) => parts,
+            other => panic!("Expected an `&&`, got {:?}.", other),
+        };
+        assert!(matches!(
+            &parts[0],
+            Guard::Value(KeyedGuard { name, value: ValueGuard::Int((Bound::Included(5), Bound::Unbounded)), .. })
+                if name == "gold"
+        ));
+        assert!(matches!(
+            &parts[1],
+            Guard::Not(inner) if matches!(
+                inner.as_ref(),
+                Guard::Value(KeyedGuard { name, value: ValueGuard::Bool(true), .. }) if name == "angry"
+            )
+        ));
+    }
+
+    #[test]
+    fn guards_group_and_compare_text_and_items() {
+        let guard = parse_guard::<Store>("(a || b) && c").unwrap();
+        assert!(matches!(guard, Guard::And(parts) if matches!(&parts[0], Guard::Or(ors) if ors.len() == 2)));
+        let guard = parse_guard::<Store>("password == \"swordfish\"").unwrap();
+        assert!(matches!(guard, Guard::Value(KeyedGuard { value: ValueGuard::Text(text), .. }) if text == "swordfish"));
+        let guard = parse_guard::<Store>("has coin > 2").unwrap();
+        assert!(matches!(
+            guard,
+            Guard::Item(ItemGuard { item, count: (Bound::Excluded(2), Bound::Unbounded) }) if item == "coin"
+        ));
+    }
+
+    #[test]
+    fn guard_errors_point_at_the_mistake() {
+        let e = parse_guard::<Store>("gold >=").unwrap_err();
+        assert_eq!(e.span, at(1, 8));
+        let e = parse_guard::<Store>("gold 5").unwrap_err();
+        assert_eq!(e.span, at(1, 6));
+        assert!(e.msg.contains("trailing"), "{}", e.msg);
+    }
+
+    #[test]
+    fn triggers_merge_changes_and_a_scene_change() {
+        let trigger = parse_trigger::<Store>("~ gold += 5\n~ give potion 2\n-> b:2").unwrap();
+        let stats = trigger.stats_changes.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].name, "gold");
+        assert!(matches!(stats[0].change, ValueChange::UpdateInt(5)));
+        assert_eq!(trigger.item_changes, Some(vec![ItemChange::new("potion".to_owned(), ItemOp::Give, 2)]));
+        assert_eq!(trigger.scene_change.unwrap().to_inner(), ("b".to_owned(), Some(2)));
+        assert_eq!(trigger.end, None);
+    }
+
+    #[test]
+    fn triggers_can_end_the_story() {
+        let trigger = parse_trigger::<Store>("# The bad one.\n~ toggle angry\n-> @end bad").unwrap();
+        assert_eq!(trigger.end.as_deref(), Some("bad"));
+        assert!(trigger.scene_change.is_none());
+    }
+
+    #[test]
+    fn trigger_errors_point_at_the_mistake() {
+        let e = parse_trigger::<Store>("~ gold += 5\n  hello").unwrap_err();
+        assert_eq!(e.span, at(2, 3));
+        let e = parse_trigger::<Store>("~ gold += true").unwrap_err();
+        assert_eq!(e.span, at(1, 11));
+    }
+
+    fn compiled(src: &str) -> Scene<Store> {
+        compile::<Store>(src).unwrap().scene
+    }
+
+    fn line(scene: &Scene<Store>, idx: usize) -> &Line<Store> {
+        scene.lines[idx].to_inner().line().unwrap()
+    }
+
+    fn trigger(scene: &Scene<Store>, idx: usize) -> &Trigger<Store> {
+        scene.lines[idx].to_inner().trigger().unwrap()
+    }
+
+    #[test]
+    fn choices_attach_to_the_line_above() {
+        let scene = compiled("ALICE: Sorry?\n* Apologize. -> b:4\n    ~ guilt += 1\n** Say nothing.\n* [if bossy] Boss.\n");
+        assert_eq!(scene.lines.len(), 1);
+        let choices = line(&scene, 0).choices.as_ref().unwrap();
+        assert_eq!(choices.choices.len(), 3);
+        assert_eq!(choices.default_choice, 1);
+        let apology = choices.choices[0].trigger.as_ref().unwrap();
+        assert_eq!(apology.scene_change.as_ref().unwrap().to_inner(), ("b".to_owned(), Some(4)));
+        assert!(matches!(apology.stats_changes.as_ref().unwrap()[0].change, ValueChange::UpdateInt(1)));
+        assert!(choices.choices[1].trigger.is_none());
+        assert!(choices.choices[2].guard.is_some());
+    }
+
+    #[test]
+    fn choices_need_a_line_and_one_default() {
+        let e = compile::<Store>("~ a = 1\n* Go.\n").unwrap_err();
+        assert_eq!(e.span, at(2, 1));
+        let e = compile::<Store>("Hm.\n** Yes.\n** No.\n").unwrap_err();
+        assert_eq!(e.span, at(3, 1));
+    }
+
+    #[test]
+    fn indented_lines_continue_the_text_above() {
+        let scene = compiled("ALICE: Hello\n    there.\n");
+        assert_eq!(line(&scene, 0).text, "Hello\nthere.");
+        let e = compile::<Store>("ALICE: Hello.\n\n    there.\n").unwrap_err();
+        assert_eq!(e.span, at(3, 5));
+    }
+
+    #[test]
+    fn directives_apply_to_the_next_line() {
+        let scene = compiled("@next b:2\n@pause 1500\n@speed 5\n@id hi\nALICE: Hi.\n@timeout 5000 2\nRun!\n** Left.\n* Right.\nBye.\n");
+        assert!(matches!(&scene.next_scene, Some(MainOrSceneChange::SceneChange(c)) if c.to_inner() == ("b".to_owned(), Some(2))));
+        let hi = line(&scene, 0);
+        assert_eq!((hi.pause, hi.speed, hi.text_id.as_deref()), (Some(1500), Some(5), Some("hi")));
+        let run = line(&scene, 1).choices.as_ref().unwrap();
+        assert_eq!((run.timeout, run.timeout_choice), (Some(5000), Some(1)));
+        let bye = line(&scene, 2);
+        assert_eq!((bye.pause, bye.speed, bye.text_id.as_deref()), (None, None, None));
+        let scene = compiled("Bye.\n@end good\n");
+        assert!(matches!(&scene.next_scene, Some(MainOrSceneChange::End(End { end })) if end == "good"));
+        let e = compile::<Store>("@pause 10\n").unwrap_err();
+        assert_eq!(e.span, at(1, 1));
+        let e = compile::<Store>("@wait 10\nHi.\n").unwrap_err();
+        assert_eq!(e.span, at(1, 1));
+    }
+
+    #[test]
+    fn consecutive_changes_merge_until_a_scene_change() {
+        let scene = compiled("Hi.\n~ a += 1\n~ give potion\n-> b\n~ c = true\n");
+        assert_eq!(scene.lines.len(), 3);
+        let first = trigger(&scene, 1);
+        assert_eq!(first.stats_changes.as_ref().unwrap().len(), 1);
+        assert_eq!(first.item_changes.as_ref().unwrap().len(), 1);
+        assert!(first.scene_change.is_some());
+        assert!(trigger(&scene, 2).scene_change.is_none());
+    }
+
+    #[test]
+    fn guarded_changes_get_a_trigger_of_their_own() {
+        let scene = compiled("[if has coin] ~ consume coin\n~ give potion\n-> c\n");
+        assert_eq!(scene.lines.len(), 2);
+        let store = Store::default();
+        assert!(scene.lines[0].try_to_inner(&store).is_none());
+        let after = scene.lines[1].try_to_inner(&store).and_then(LineOrTrigger::trigger).unwrap();
+        assert_eq!(after.item_changes, Some(vec![ItemChange::new("potion".to_owned(), ItemOp::Give, 1)]));
+        assert!(after.scene_change.is_some());
+        // Unguarded changes still take in the ones after them, but not guarded ones.
+        let scene = compiled("~ a += 1\n[if b] ~ c += 1\n");
+        assert_eq!(scene.lines.len(), 2);
+    }
+
+    #[test]
+    fn taking_away_too_much_is_an_error() {
+        let e = compile::<Store>("~ gold -= -9223372036854775808\n").unwrap_err();
+        assert_eq!(e.span, at(1, 11));
+        let scene = compiled("~ gold -= 9223372036854775807\n");
+        assert!(matches!(trigger(&scene, 0).stats_changes.as_ref().unwrap()[0].change, ValueChange::UpdateInt(-9223372036854775807)));
+    }
+}
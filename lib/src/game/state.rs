@@ -1,8 +1,8 @@
 use serde::{Deserialize, Serialize};
 use crate::game::{guard, trigger};
 
-pub trait KeyedStore: Serialize + for<'de> Deserialize<'de> + std::fmt::Debug {
-    type KeyEnum: Serialize + for<'de> Deserialize<'de> + std::fmt::Debug;
+pub trait KeyedStore: Serialize + for<'de> Deserialize<'de> + std::fmt::Debug + Sized {
+    type KeyEnum: Serialize + for<'de> Deserialize<'de> + std::fmt::Debug + Clone;
 
     fn check_guard(&self, guard: &guard::Guard<Self>) -> bool {
         match guard {
@@ -13,15 +13,25 @@ pub trait KeyedStore: Serialize + for<'de> Deserialize<'de> + std::fmt::Debug {
                 gg.iter().any(|g| self.check_guard(g))
             }
             guard::Guard::Not(g) => {
-                !self.check_guard(&*g)
+                !self.check_guard(g)
             }
             guard::Guard::Value(g) => {
                 self.check_keyed_guard(g)
             }
+            guard::Guard::Item(g) => {
+                g.check(self)
+            }
         }
     }
 
     fn check_keyed_guard(&self, guard: &guard::KeyedGuard<Self>) -> bool;
 
     fn update_with_value(&mut self, change: &trigger::StatChange<Self>);
+
+    /// Stores text the player typed in.
+    fn store_text(&mut self, name: &Self::KeyEnum, text: String);
+
+    fn item_count(&self, item: &str) -> u32;
+
+    fn set_item_count(&mut self, item: &str, count: u32);
 }
@@ -1,19 +1,38 @@
-use crate::game::state::KeyedStore;
+use crate::game::{item, state::KeyedStore};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Sets are written as the value, and updates as `{add: value}`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum ValueChange {
     SetBool(bool),
     SetInt(i64),
     SetFloat(f64),
-    UpdateInt(i64),
-    UpdateFloat(f64),
+    UpdateInt(#[serde(with = "add")] i64),
+    UpdateFloat(#[serde(with = "add")] f64),
     Custom(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+mod add {
+    use super::*;
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Add<N> {
+        add: N,
+    }
+
+    pub fn serialize<N: Serialize, S: Serializer>(add: &N, s: S) -> Result<S::Ok, S::Error> {
+        Add { add }.serialize(s)
+    }
+
+    pub fn deserialize<'de, N: Deserialize<'de>, D: Deserializer<'de>>(de: D) -> Result<N, D::Error> {
+        Add::deserialize(de).map(|Add { add }| add)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StatChange<T: KeyedStore> {
     pub name: T::KeyEnum,
     pub change: ValueChange,
@@ -21,26 +40,49 @@ pub struct StatChange<T: KeyedStore> {
     _phantom: std::marker::PhantomData<T>,
 }
 
+impl<T: KeyedStore> StatChange<T> {
+    pub fn new(name: T::KeyEnum, change: ValueChange) -> Self {
+        Self {
+            name,
+            change,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
 // TODO Consider dynamic new scenes.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SceneChange {
     name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     target_line: Option<usize>
 }
 
 impl SceneChange {
+    pub fn new(name: String, target_line: Option<usize>) -> Self {
+        Self { name, target_line }
+    }
+
     pub fn to_inner(&self) -> (String, Option<usize>) {
-        (self.name.to_owned(), self.target_line.clone())
+        (self.name.to_owned(), self.target_line)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-#[serde(bound = "")]
+// Unknown fields are rejected, so that misspelled ones aren't silently ignored.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "", deny_unknown_fields)]
 pub struct Trigger<T: KeyedStore> {
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub stats_changes: Option<Vec<StatChange<T>>>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub item_changes: Option<Vec<item::ItemChange>>,
+    /// Ids of achievements to unlock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unlocks: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub scene_change: Option<SceneChange>,
+    /// Id of the ending to end the story with. Takes precedence over `scene_change`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
 }
 
@@ -14,6 +14,43 @@ pub enum Error {
     Input(uial::input::Err),
     Display(uial::display::Err),
     Resolution(exec::ResolutionErr),
+    /// Setting up something the game runs in, like a server socket, failed.
+    Startup(std::io::Error),
+}
+impl Error {
+    /// The load diagnostic behind this error, if there is one.
+    pub fn diagnostic(&self) -> Option<&game::Diagnostic> {
+        match self {
+            Error::Initialization(e) => e.diagnostic(),
+            Error::Load(e) => e.diagnostic(),
+            Error::Resolution(exec::ResolutionErr::SceneLoad { err, .. }) => err.diagnostic(),
+            _ => None,
+        }
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Initialization(_) => write!(f, "Failed to initialize the game."),
+            Error::Load(_) => write!(f, "Failed to load game data."),
+            Error::Input(_) => write!(f, "Failed to read input."),
+            Error::Display(_) => write!(f, "Failed to display the game."),
+            Error::Resolution(_) => write!(f, "Failed to resolve the player's action."),
+            Error::Startup(_) => write!(f, "Failed to start."),
+        }
+    }
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Initialization(e) => Some(e),
+            Error::Load(e) => Some(e),
+            Error::Input(e) => Some(e),
+            Error::Display(e) => Some(e),
+            Error::Resolution(e) => Some(e),
+            Error::Startup(e) => Some(e),
+        }
+    }
 }
 impl From<game::InitErr> for Error {
     fn from(e: game::InitErr) -> Self {
@@ -35,6 +72,11 @@ impl From<uial::display::Err> for Error {
         Error::Display(e)
     }
 }
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Startup(e)
+    }
+}
 impl From<exec::ResolutionErr> for Error {
     fn from(e: exec::ResolutionErr) -> Self {
         Error::Resolution(e)
@@ -3,4 +3,5 @@
 //! This is responsible to selectively omitting things and handling how things draw to screen.
 
 pub mod display;
+pub mod event;
 pub mod input;
@@ -4,8 +4,11 @@ pub enum RenderMode<T> {
     Ignore,
 }
 
+/// Picks how `A`, `B` and `C` get turned into something displayable. Usually implemented on a
+/// marker type, since the output borrows from its inputs.
 pub trait RenderGroup<'a, A, B, C> {
-    fn create(a: &'a A, b: &'a B, c: C) -> Self;
+    type Output: std::fmt::Display;
+    fn create(a: &'a A, b: &'a B, c: C) -> Self::Output;
 }
 
 #[derive(Debug)]
@@ -19,6 +22,22 @@ impl From<std::io::Error> for Err {
     }
 }
 
+impl std::fmt::Display for Err {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Err::IO(_) => write!(f, "Couldn't write to the terminal."),
+        }
+    }
+}
+
+impl std::error::Error for Err {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Err::IO(e) => Some(e),
+        }
+    }
+}
+
 pub trait Display<A, B, C, R> {
     fn display(&mut self, content: &A, cfg: &B, d: C) -> Result<(), Err>;
 }
@@ -28,10 +47,14 @@ pub struct CmdDisplay<A, B, C, R> {
 }
 
 impl<A, B, C, R> Display<A, B, C, R> for CmdDisplay<A, B, C, R>
-    where R: for <'a> RenderGroup<'a, A, B, C> + std::fmt::Display
+    where R: for <'a> RenderGroup<'a, A, B, C>
 {
     fn display(&mut self, a: &A, b: &B, c: C) -> Result<(), Err> {
-        println!("{}", R::create(a, b, c));
+        use std::io::Write;
+        // Renders can be partial lines, so no newline is added, and stdout needs to be flushed.
+        let mut stdout = std::io::stdout();
+        write!(stdout, "{}", R::create(a, b, c))?;
+        stdout.flush()?;
         Ok(())
     }
 }
@@ -48,7 +71,7 @@ pub struct RawCmdDisplay<A, B, C, R> {
 }
 
 impl<A, B, C, R> Display<A, B, C, R> for RawCmdDisplay<A, B, C, R>
-    where R: for <'a> RenderGroup<'a, A, B, C> + std::fmt::Display
+    where R: for <'a> RenderGroup<'a, A, B, C>
 {
     fn display(&mut self, a: &A, b: &B, c: C) -> Result<(), Err> {
         if let Some(raw_term) = self.raw_term.as_mut() {
@@ -89,3 +112,41 @@ pub fn raw_cmd_line<A, B, C, R>() -> RawCmdDisplay<A, B, C, R> {
         raw_term,
     }
 }
+
+/// The last render, and how many renders there have been, so that readers can wait for the next.
+#[derive(Default)]
+pub struct Frame {
+    pub count: u64,
+    pub text: String,
+}
+
+/// Where a `SharedDisplay` keeps its frames, for other threads to read.
+pub type Frames = std::sync::Arc<(std::sync::Mutex<Frame>, std::sync::Condvar)>;
+
+/// Keeps the last render instead of writing it anywhere, such as for a server to hand out.
+pub struct SharedDisplay<A, B, C, R> {
+    frames: Frames,
+    phantom: std::marker::PhantomData<(A, B, C, R)>,
+}
+
+impl<A, B, C, R> Display<A, B, C, R> for SharedDisplay<A, B, C, R>
+    where R: for <'a> RenderGroup<'a, A, B, C>
+{
+    fn display(&mut self, a: &A, b: &B, c: C) -> Result<(), Err> {
+        let (frame, rendered) = &*self.frames;
+        let mut frame = frame.lock().expect("Not a poisoned mutex.");
+        frame.count += 1;
+        frame.text = R::create(a, b, c).to_string();
+        rendered.notify_all();
+        Ok(())
+    }
+}
+
+/// Takes `frames` rather than making them, since displays (holding onto the sim's types) usually
+/// can't be sent across threads, unlike the frames.
+pub fn shared<A, B, C, R>(frames: Frames) -> SharedDisplay<A, B, C, R> {
+    SharedDisplay {
+        frames,
+        phantom: std::marker::PhantomData,
+    }
+}
@@ -0,0 +1,156 @@
+//! Multiplexes player input with the things that can happen without it, such as timers and
+//! changes to game files on disk.
+use crate::uial::input::{self, Input, SystemAction};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant, SystemTime},
+};
+
+#[derive(Debug)]
+pub enum Event<A> {
+    Input(SystemAction<A>),
+    /// The deadline requested by the sim has passed.
+    Timer,
+    /// Files under a watched directory were created, modified or removed.
+    FilesChanged(Vec<PathBuf>),
+}
+
+pub trait EventSource<A> {
+    /// Blocks until the next event. Sources that support timers return `Event::Timer` once
+    /// `deadline` passes, if nothing else happened first.
+    fn next_event(&mut self, deadline: Option<Instant>) -> Result<Event<A>, input::Err>;
+}
+
+/// Turns a blocking `Input` into an event source. Input can't be interrupted, so timers only fire
+/// if the deadline has already passed by the time the next event is requested.
+pub struct Blocking<I>(pub I);
+
+impl<A, I: Input<A>> EventSource<A> for Blocking<I> {
+    fn next_event(&mut self, deadline: Option<Instant>) -> Result<Event<A>, input::Err> {
+        if deadline.is_some_and(|d| d <= Instant::now()) {
+            return Ok(Event::Timer);
+        }
+        self.0.next_action().map(Event::Input)
+    }
+}
+
+/// Collects events from background threads, one per source.
+pub struct Multiplexer<A> {
+    tx: Option<mpsc::Sender<Result<Event<A>, input::Err>>>,
+    rx: mpsc::Receiver<Result<Event<A>, input::Err>>,
+}
+
+impl<A: Send + 'static> Multiplexer<A> {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx: Some(tx), rx }
+    }
+
+    fn sender(&self) -> mpsc::Sender<Result<Event<A>, input::Err>> {
+        self.tx.clone().expect("Sources are only added before the first event.")
+    }
+
+    /// Reads input on its own thread. Takes a constructor, since inputs (like stdin) usually can't
+    /// be sent across threads.
+    pub fn with_input<I, F>(self, make_input: F) -> Self
+    where
+        I: Input<A>,
+        F: FnOnce() -> I + Send + 'static,
+    {
+        let tx = self.sender();
+        std::thread::spawn(move || {
+            let mut input = make_input();
+            loop {
+                let action = input.next_action();
+                let done = matches!(action, Ok(SystemAction::Exit) | Err(_));
+                if tx.send(action.map(Event::Input)).is_err() || done {
+                    break;
+                }
+            }
+            log::debug!("Input thread finished.");
+        });
+        self
+    }
+
+    /// Polls `dir` (recursively) for changes every `interval`.
+    pub fn with_watch(self, dir: PathBuf, interval: Duration) -> Self {
+        let tx = self.sender();
+        std::thread::spawn(move || {
+            let mut last = snapshot(dir.as_path());
+            loop {
+                std::thread::sleep(interval);
+                let curr = snapshot(dir.as_path());
+                let changed: Vec<_> = curr
+                    .iter()
+                    .filter(|(p, t)| last.get(*p) != Some(t))
+                    .map(|(p, _)| p.clone())
+                    .chain(last.keys().filter(|p| !curr.contains_key(*p)).cloned())
+                    .collect();
+                last = curr;
+                if changed.is_empty() {
+                    continue;
+                }
+                log::debug!("Watched files changed: {:?}", changed);
+                if tx.send(Ok(Event::FilesChanged(changed))).is_err() {
+                    break;
+                }
+            }
+        });
+        self
+    }
+}
+
+impl<A: Send + 'static> Default for Multiplexer<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> EventSource<A> for Multiplexer<A> {
+    fn next_event(&mut self, deadline: Option<Instant>) -> Result<Event<A>, input::Err> {
+        // Drop our own sender, so that running out of sources is noticed.
+        self.tx = None;
+        let received = match deadline {
+            Some(deadline) => {
+                match self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    Ok(event) => Some(event),
+                    Err(mpsc::RecvTimeoutError::Timeout) => return Ok(Event::Timer),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                        return Ok(Event::Timer);
+                    }
+                }
+            }
+            None => self.rx.recv().ok(),
+        };
+        // Nothing left that could produce an event.
+        received.unwrap_or(Ok(Event::Input(SystemAction::Exit)))
+    }
+}
+
+fn snapshot(dir: &Path) -> HashMap<PathBuf, SystemTime> {
+    fn walk(dir: &Path, out: &mut HashMap<PathBuf, SystemTime>) {
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Couldn't watch {} due to {:?}.", dir.display(), e);
+                return;
+            }
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(m) if m.is_dir() => walk(path.as_path(), out),
+                Ok(m) => {
+                    out.insert(path, m.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    let mut out = HashMap::new();
+    walk(dir, &mut out);
+    out
+}
@@ -14,6 +14,23 @@ impl From<String> for Err {
     }
 }
 
+impl std::fmt::Display for Err {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Err::IOErr(_) => write!(f, "Couldn't read input."),
+            Err::OtherErr(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+impl std::error::Error for Err {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Err::IOErr(e) => Some(e),
+            Err::OtherErr(_) => None,
+        }
+    }
+}
+
 pub trait CustomAction: Sized {
     fn parse_input(cmd: Option<String>) -> Result<SystemAction<Self>, String>;
 }
@@ -21,7 +38,12 @@ pub trait CustomAction: Sized {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SystemAction<A> {
     Exit,
+    /// Suspends the story for the system menu, such as on Esc.
+    Menu,
     Action(A),
+    /// Input that couldn't be read as an action, and why. Shown rather than ending the session,
+    /// since the next input may well be fine.
+    Invalid(String),
 }
 
 pub trait Input<Action> {
@@ -41,7 +63,7 @@ impl<'a, Action: CustomAction> Input<Action> for CmdInput<'a, Action> {
     }
 }
 
-pub fn cmd_line<'a, Action: CustomAction>() -> CmdInput<'a, Action> {
+pub fn cmd_line<'a, Action>() -> CmdInput<'a, Action> {
     use std::io::BufRead;
     // Self referential struct...?
     let stdin = Box::leak(Box::new(std::io::stdin()));
@@ -56,7 +78,7 @@ pub fn cmd_line<'a, Action: CustomAction>() -> CmdInput<'a, Action> {
 impl<'a, Action> Drop for CmdInput<'a, Action> {
     fn drop(&mut self) {
         unsafe {
-            Box::from_raw(self.stdin as *mut std::io::Stdin);
+            drop(Box::from_raw(self.stdin as *mut std::io::Stdin));
         }
     }
 }
@@ -77,3 +99,67 @@ pub fn raw_cmd_line<'a, Action: CustomAction>() -> RawCmdInput<'a, Action> {
         backup_input: cmd_line(),
     }
 }
+
+/// Actions every game has, as they're written in JSON.
+#[derive(serde::Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum JsonSystemAction {
+    Exit,
+    Menu,
+}
+
+/// Reads an action written in JSON. Besides the game's own actions, `{"action": "menu"}` and
+/// `{"action": "exit"}` are understood.
+pub fn parse_json<Action: serde::de::DeserializeOwned>(json: &str) -> Result<SystemAction<Action>, String> {
+    if let Ok(action) = serde_json::from_str::<JsonSystemAction>(json) {
+        return Ok(match action {
+            JsonSystemAction::Exit => SystemAction::Exit,
+            JsonSystemAction::Menu => SystemAction::Menu,
+        });
+    }
+    serde_json::from_str(json)
+        .map(SystemAction::Action)
+        .map_err(|e| format!("Couldn't read {:?} as an action due to: {}", json, e))
+}
+
+/// Reads one JSON action per line, for frontends driving the game instead of a player. Anything
+/// that isn't an action is sent back as an error, so the frontend can tell what it got wrong.
+pub struct JsonInput<'a, Action> {
+    lines: CmdInput<'a, Action>,
+}
+
+impl<'a, Action: serde::de::DeserializeOwned> Input<Action> for JsonInput<'a, Action> {
+    fn next_action(&mut self) -> Result<SystemAction<Action>, Err> {
+        // Blank lines are skipped, rather than being errors.
+        loop {
+            let line = match self.lines.lines.next().transpose()? {
+                Some(line) => line,
+                None => return Ok(SystemAction::Exit),
+            };
+            if !line.trim().is_empty() {
+                return Ok(parse_json(&line).unwrap_or_else(SystemAction::Invalid));
+            }
+        }
+    }
+}
+
+pub fn json_lines<'a, Action: serde::de::DeserializeOwned>() -> JsonInput<'a, Action> {
+    JsonInput { lines: cmd_line() }
+}
+
+/// Takes actions sent from elsewhere, such as a server handling requests for this game. Runs out,
+/// exiting, once every sender is gone.
+pub struct ChannelInput<Action> {
+    rx: std::sync::mpsc::Receiver<SystemAction<Action>>,
+}
+
+impl<Action> Input<Action> for ChannelInput<Action> {
+    fn next_action(&mut self) -> Result<SystemAction<Action>, Err> {
+        Ok(self.rx.recv().unwrap_or(SystemAction::Exit))
+    }
+}
+
+pub fn channel<Action>() -> (std::sync::mpsc::Sender<SystemAction<Action>>, ChannelInput<Action>) {
+    let (tx, rx) = std::sync::mpsc::channel();
+    (tx, ChannelInput { rx })
+}